@@ -1,18 +1,41 @@
+use crate::error::Error;
+use crate::raw::panicking;
 use crate::raw::AsyncRawFusedSync;
-use crate::raw::{AsyncRawFused, RawOnceState};
-use std::cell::UnsafeCell;
-use std::future::Future;
-use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
-use std::panic::{RefUnwindSafe, UnwindSafe};
-use std::sync::{PoisonError, TryLockError};
-use std::thread::panicking;
+use crate::raw::{AsyncRawFused, RawOnceState, ScopeId};
+use core::cell::UnsafeCell;
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use core::task::Poll;
+#[cfg(feature = "std")]
+use std::sync::PoisonError;
 
 pub struct AsyncFused<R: AsyncRawFused, T> {
     raw: R,
+    policy: PoisonPolicy,
     data: UnsafeCell<T>,
 }
 
+/// What happens when a write guard is dropped without being fused.
+///
+/// Without the `std` feature a panic cannot be observed, so the guard of a panicking writer
+/// unlocks rather than poisons under every policy. Cancelled initializers still poison under
+/// [`PoisonOnCancel`](Self::PoisonOnCancel).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PoisonPolicy {
+    /// A panic poisons the lock; a cancelled writer unlocks it for the next one.
+    #[default]
+    PoisonOnPanic,
+    /// Neither a panic nor a cancellation poisons the lock, so the next writer retries.
+    RetryOnPanic,
+    /// Both a panic and a cancelled initializer poison the lock. A guard that is dropped
+    /// without an initializer in flight still just unlocks it.
+    PoisonOnCancel,
+}
+
 pub enum AsyncFusedEntry<'a, R: AsyncRawFused, T> {
     Write(AsyncFusedGuard<'a, R, T>),
     Read(&'a T),
@@ -20,17 +43,51 @@ pub enum AsyncFusedEntry<'a, R: AsyncRawFused, T> {
 
 pub struct AsyncFusedGuard<'a, R: AsyncRawFused, T> {
     fused: Option<&'a AsyncFused<R, T>>,
+    /// Set while an initializer holding the guard is in flight, so that dropping the guard
+    /// cancels it.
+    initializing: bool,
     marker: PhantomData<(&'a mut T, R::GuardMarker)>,
 }
 
 impl<'a, R: AsyncRawFused, T> AsyncFusedGuard<'a, R, T> {
+    /// Takes the lock out of the guard, so that it is released by the caller rather than by
+    /// `drop`.
+    fn release(&mut self) -> &'a AsyncFused<R, T> {
+        self.fused.take().unwrap()
+    }
+    /// Releases the write lock and poisons it, regardless of the [`PoisonPolicy`].
+    pub fn poison(mut self) {
+        unsafe { self.release().raw.unlock_poison() }
+    }
     pub fn fuse(mut self) -> &'a T {
         unsafe {
-            let once = self.fused.take().unwrap();
+            let once = self.release();
             once.raw.unlock_fuse();
             &*once.data.get()
         }
     }
+    /// The number of other callers waiting for this guard to be released.
+    pub fn waiters(&self) -> usize {
+        self.fused.unwrap().raw.waiters()
+    }
+    /// Identifies this lock for [`WriteScope`], so that futures polled while holding the guard
+    /// cannot wait on the lock themselves.
+    pub(crate) fn scope_id(&self) -> ScopeId {
+        ScopeId::new(&self.fused.unwrap().raw)
+    }
+    /// Hands the guard to an initializer, which is polled in a
+    /// [`WriteScope`](crate::raw::WriteScope) for this lock.
+    ///
+    /// Until [`end_init`](Self::end_init), dropping the guard cancels the initializer, which
+    /// poisons the lock under [`PoisonPolicy::PoisonOnCancel`].
+    pub(crate) fn begin_init(&mut self) {
+        self.initializing = true;
+    }
+    /// Marks the initializer as finished or deliberately abandoned, so that dropping the guard
+    /// unlocks the lock again.
+    pub(crate) fn end_init(&mut self) {
+        self.initializing = false;
+    }
 }
 
 impl<'a, R: AsyncRawFused, T> AsyncFusedEntry<'a, R, T> {
@@ -61,27 +118,37 @@ impl<'a, R: AsyncRawFused, T> DerefMut for AsyncFusedGuard<'a, R, T> {
 
 impl<R: AsyncRawFused, T> AsyncFused<R, T> {
     pub const fn new(x: T) -> Self {
+        Self::new_with_policy(x, PoisonPolicy::PoisonOnPanic)
+    }
+    pub const fn new_with_policy(x: T, policy: PoisonPolicy) -> Self {
         AsyncFused {
             raw: R::UNLOCKED,
+            policy,
             data: UnsafeCell::new(x),
         }
     }
     pub const fn new_read(x: T) -> Self {
         AsyncFused {
             raw: R::READ,
+            policy: PoisonPolicy::PoisonOnPanic,
             data: UnsafeCell::new(x),
         }
     }
     pub const fn poisoned(x: T) -> Self {
         AsyncFused {
             raw: R::POISON,
+            policy: PoisonPolicy::PoisonOnPanic,
             data: UnsafeCell::new(x),
         }
     }
-    unsafe fn make_entry(&self, raw: RawOnceState) -> AsyncFusedEntry<R, T> {
+    pub fn policy(&self) -> PoisonPolicy {
+        self.policy
+    }
+    unsafe fn make_entry(&self, raw: RawOnceState) -> AsyncFusedEntry<'_, R, T> {
         match raw {
             RawOnceState::Vacant => AsyncFusedEntry::Write(AsyncFusedGuard {
                 fused: Some(self),
+                initializing: false,
                 marker: PhantomData,
             }),
             RawOnceState::Occupied => AsyncFusedEntry::Read(&*self.data.get()),
@@ -90,44 +157,59 @@ impl<R: AsyncRawFused, T> AsyncFused<R, T> {
     fn raw(&self) -> &R {
         &self.raw
     }
-    pub async fn write_checked(&self) -> Result<AsyncFusedEntry<R, T>, TryLockError<()>> {
+    /// Returns [`Error::Cycle`] rather than waiting forever if the caller is polled by an
+    /// initializer of this lock.
+    pub async fn write_checked(&self) -> Result<AsyncFusedEntry<'_, R, T>, Error> {
+        // Once fused, skip building the raw future, which some raw locks box.
+        if let Ok(RawOnceState::Occupied) = self.raw.try_read_checked() {
+            return unsafe { Ok(self.make_entry(RawOnceState::Occupied)) };
+        }
         unsafe { Ok(self.make_entry(self.raw().write_checked().await?)) }
     }
     fn write_checked_is_send(
         &self,
-    ) -> impl Send + Future<Output = Result<AsyncFusedEntry<R, T>, TryLockError<()>>>
+    ) -> impl Send + Future<Output = Result<AsyncFusedEntry<'_, R, T>, Error>>
     where
         R: AsyncRawFusedSync,
         T: Sync + Send,
     {
         self.write_checked()
     }
-    pub async fn write(&self) -> AsyncFusedEntry<R, T> {
+    pub async fn write(&self) -> AsyncFusedEntry<'_, R, T> {
         self.write_checked().await.unwrap()
     }
-    fn write_is_send(&self) -> impl Send + Future<Output = AsyncFusedEntry<R, T>>
+    fn write_is_send(&self) -> impl Send + Future<Output = AsyncFusedEntry<'_, R, T>>
     where
         R: AsyncRawFusedSync,
         T: Sync + Send,
     {
         self.write()
     }
-    pub fn try_write_checked(&self) -> Result<Option<AsyncFusedEntry<R, T>>, TryLockError<()>> {
-        unsafe { Ok(self.raw.try_write_checked()?.map(|e| self.make_entry(e))) }
+    /// Like [`write_checked`](Self::write_checked), but blocks the current thread using
+    /// [`AsyncRawFused::write_checked_blocking`]. Internal to the blocking accessors of the
+    /// cells, which keep their signatures.
+    #[cfg(feature = "std")]
+    pub(crate) fn write_checked_blocking(&self) -> Result<AsyncFusedEntry<'_, R, T>, Error> {
+        unsafe { Ok(self.make_entry(self.raw.write_checked_blocking()?)) }
     }
-    pub fn try_write(&self) -> Option<AsyncFusedEntry<R, T>> {
-        self.try_write_checked().unwrap()
+    pub fn try_write_checked(&self) -> Result<AsyncFusedEntry<'_, R, T>, Error> {
+        unsafe { Ok(self.make_entry(self.raw.try_write_checked()?)) }
+    }
+    /// Returns `None` if another caller holds the write lock.
+    pub fn try_write(&self) -> Option<AsyncFusedEntry<'_, R, T>> {
+        match self.try_write_checked() {
+            Ok(entry) => Some(entry),
+            Err(Error::WouldBlock) => None,
+            Err(e) => panic!("{}", e),
+        }
     }
     pub async fn read_or_fuse(&self, init: impl FnOnce(&mut T)) -> &T {
         self.read_or_fuse_checked(init).await.unwrap()
     }
-    pub async fn read_or_fuse_checked(
-        &self,
-        init: impl FnOnce(&mut T),
-    ) -> Result<&T, TryLockError<()>> {
+    pub async fn read_or_fuse_checked(&self, init: impl FnOnce(&mut T)) -> Result<&T, Error> {
         Ok(self.write_checked().await?.or_fuse(init))
     }
-    pub fn try_read_checked(&self) -> Result<Option<&T>, PoisonError<()>> {
+    pub fn try_read_checked(&self) -> Result<Option<&T>, Error> {
         unsafe {
             Ok(match self.raw.try_read_checked()? {
                 RawOnceState::Vacant => None,
@@ -135,20 +217,67 @@ impl<R: AsyncRawFused, T> AsyncFused<R, T> {
             })
         }
     }
-    // pub async fn read_checked(&self) -> Result<Option<&T>, TryLockError<()>> {
-    //     unsafe {
-    //         Ok(match self.raw.read_checked().await? {
-    //             RawOnceState::Vacant => None,
-    //             RawOnceState::Occupied => Some(&*self.data.get())
-    //         })
-    //     }
-    // }
+    /// Waits for any in-progress writer to finish, then returns the value if it was fused.
+    /// Unlike [`write_checked`](Self::write_checked), this never acquires the write lock. Like
+    /// it, this returns [`Error::Cycle`] when called by the initializer itself.
+    pub async fn read_checked(&self) -> Result<Option<&T>, Error> {
+        unsafe {
+            Ok(match self.raw.read_checked().await? {
+                RawOnceState::Vacant => None,
+                RawOnceState::Occupied => Some(&*self.data.get()),
+            })
+        }
+    }
+    fn read_checked_is_send(&self) -> impl Send + Future<Output = Result<Option<&T>, Error>>
+    where
+        R: AsyncRawFusedSync,
+        T: Sync + Send,
+    {
+        self.read_checked()
+    }
     pub fn try_read(&self) -> Option<&T> {
         self.try_read_checked().unwrap()
     }
-    // pub async fn read(&self) -> Option<&T> {
-    //     self.read_checked().await.unwrap()
-    // }
+    pub async fn read(&self) -> Option<&T> {
+        self.read_checked().await.unwrap()
+    }
+    pub fn is_poisoned(&self) -> bool {
+        matches!(self.raw.try_read_checked(), Err(Error::Poisoned))
+    }
+    /// Allows the next writer to acquire a poisoned lock. The data is left as the panicking
+    /// writer left it.
+    pub fn clear_poison(&self) {
+        self.raw.clear_poison();
+    }
+    /// Like [`write`](Self::write), but a poisoned lock is cleared and its guard is handed back
+    /// inside a [`PoisonError`], so that the caller can repair the data before unlocking.
+    #[cfg(feature = "std")]
+    pub async fn write_recover(
+        &self,
+    ) -> Result<AsyncFusedEntry<'_, R, T>, PoisonError<AsyncFusedGuard<'_, R, T>>> {
+        self.write_recover_guard().await.map_err(PoisonError::new)
+    }
+    /// [`write_recover`](Self::write_recover) without the `std`-only `PoisonError` wrapper.
+    pub(crate) async fn write_recover_guard(
+        &self,
+    ) -> Result<AsyncFusedEntry<'_, R, T>, AsyncFusedGuard<'_, R, T>> {
+        let mut cleared = false;
+        loop {
+            match self.write_checked().await {
+                Ok(AsyncFusedEntry::Write(guard)) if cleared => return Err(guard),
+                Ok(entry) => return Ok(entry),
+                Err(Error::Poisoned) => cleared |= self.raw.clear_poison(),
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+    /// Returns the lock to its unlocked state, whether it was fused or poisoned.
+    pub fn reset(&mut self) {
+        self.raw = R::UNLOCKED;
+    }
     pub fn into_inner(self) -> T {
         self.data.into_inner()
     }
@@ -175,7 +304,12 @@ impl<'a, R: AsyncRawFused, T> Drop for AsyncFusedGuard<'a, R, T> {
     fn drop(&mut self) {
         unsafe {
             if let Some(once) = self.fused {
-                if panicking() {
+                let poison = match once.policy {
+                    PoisonPolicy::PoisonOnPanic => panicking(),
+                    PoisonPolicy::RetryOnPanic => false,
+                    PoisonPolicy::PoisonOnCancel => self.initializing || panicking(),
+                };
+                if poison {
                     once.raw.unlock_poison();
                 } else {
                     once.raw.unlock();
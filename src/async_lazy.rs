@@ -1,49 +1,160 @@
 use crate::async_fused::{AsyncFused, AsyncFusedEntry, AsyncFusedGuard};
 // use crate::const_box::{ConstBox, ConstBoxFuture};
 // use crate::detached::{detached, detached_lazy, DetachedLazy};
-use crate::detached::DetachedFuture;
+#[cfg(feature = "std")]
+use crate::blocking::block_on;
+use crate::detached::{BoxDetached, DetachedFuture, LocalBoxDetached, SpawnOnPoll, Spawner};
+use crate::raw::panicking;
+use core::cell::{Cell, UnsafeCell};
+use core::fmt::{Debug, Formatter};
+use core::future::{poll_fn, Future};
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::time::Duration;
 use futures::future::BoxFuture;
 use futures::FutureExt;
-use std::cell::{Cell, UnsafeCell};
-use std::future::{poll_fn, Future};
-use std::marker::Unsize;
-use std::mem::MaybeUninit;
-use std::pin::Pin;
-use std::thread::panicking;
+#[cfg(feature = "std")]
+use tokio::time::timeout;
 // use crate::pure_future::PureFuture;
 // use crate::async_once::{AsyncOnce, AsyncOnceEntry};
-use crate::raw::{AsyncRawFused, RawOnceState};
+use crate::error::Error;
+use crate::raw::{AsyncRawFused, RawOnceState, WriteScope};
 // use crate::spawned_future::SpawnedFuture;
 use crate::raw::AsyncRawFusedSync;
 use crate::thunk::{OptionThunk, Thunk};
 
-pub struct AsyncLazy<R: AsyncRawFused, F: DetachedFuture> {
-    fused: AsyncFused<R, Thunk<F::Output, F>>,
+/// A value initialized by a future the first time it is requested.
+///
+/// The future is either passed in ready-made with [`new`](Self::new), or built by a closure on
+/// the first call to [`get`](Self::get) with [`new_with`](Self::new_with).
+pub struct AsyncLazy<R: AsyncRawFused, F: DetachedFuture, I = fn() -> F> {
+    fused: AsyncFused<R, Thunk<F::Output, F, I>>,
 }
 
-impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static + Send>
-    AsyncLazy<R, F>
-{
-    pub fn new(f: F) -> Self {
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static + Send> AsyncLazy<R, F> {
+    pub const fn new(f: F) -> Self {
         AsyncLazy {
             fused: AsyncFused::new(Thunk::new(f)),
         }
     }
+}
 
-    pub async fn get(&self) -> &T {
-        match self.fused.write().await {
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static + Send, I>
+    AsyncLazy<R, F, I>
+where
+    I: FnOnce() -> F,
+{
+    /// Creates a cell whose future is built by `init` on the first call to [`get`](Self::get),
+    /// e.g. to spawn it on first use. Usable in a `static` with a function pointer or a
+    /// non-capturing closure.
+    pub const fn new_with(init: I) -> Self {
+        AsyncLazy {
+            fused: AsyncFused::new(Thunk::new_with(init)),
+        }
+    }
+
+    pub async fn get_checked(&self) -> Result<&T, Error> {
+        Ok(match self.fused.write_checked().await? {
             AsyncFusedEntry::Write(mut guard) => {
-                guard.get_or_init().await;
+                guard.begin_init();
+                WriteScope::new(guard.scope_id(), guard.get_or_init()).await;
                 guard.fuse().get().unwrap()
             }
             AsyncFusedEntry::Read(x) => x.get().unwrap(),
+        })
+    }
+
+    pub async fn get(&self) -> &T {
+        self.get_checked().await.unwrap()
+    }
+
+    /// Blocks the current thread until the value is ready, driving the initializer inline if no
+    /// other task is.
+    ///
+    /// # Panics
+    /// With the `tokio-rt` feature, if the value is not ready and this is called from inside a
+    /// tokio runtime.
+    #[cfg(feature = "std")]
+    pub fn get_blocking(&self) -> &T {
+        match self.try_get() {
+            Some(x) => x,
+            None => match self.fused.write_checked_blocking().unwrap() {
+                AsyncFusedEntry::Write(mut guard) => {
+                    block_on(WriteScope::new(guard.scope_id(), guard.get_or_init()));
+                    guard.fuse().get().unwrap()
+                }
+                AsyncFusedEntry::Read(x) => x.get().unwrap(),
+            },
         }
     }
+
+    /// Like [`try_get`](Self::try_get), but returns [`Error::Poisoned`] for a poisoned cell
+    /// rather than `None`.
+    pub fn try_get_checked(&self) -> Result<Option<&T>, Error> {
+        Ok(self.fused.try_read_checked()?.and_then(|x| x.get()))
+    }
+
+    /// Returns the value if it has been initialized, without waiting or locking.
+    pub fn try_get(&self) -> Option<&T> {
+        self.try_get_checked().ok()?
+    }
+
+    /// Like [`get_checked`](Self::get_checked), but gives up with [`Error::TimedOut`] after
+    /// `duration`. Giving up only stops this caller: a spawned initializer keeps running, and
+    /// one that this caller was polling inline pauses until the next caller resumes it.
+    #[cfg(feature = "std")]
+    pub async fn get_timeout(&self, duration: Duration) -> Result<&T, Error> {
+        timeout(duration, self.get_checked())
+            .await
+            .map_err(|_| Error::TimedOut)?
+    }
+}
+
+impl<R: AsyncRawFused, T: 'static + Send> AsyncLazy<R, BoxDetached<T>> {
+    /// Creates a cell named by its output type alone, boxing the initializer.
+    pub fn new_boxed(f: impl 'static + Send + Future<Output = T>) -> Self {
+        AsyncLazy::new(BoxDetached::new(f))
+    }
+}
+
+impl<R: AsyncRawFused, T: 'static + Send> AsyncLazy<R, LocalBoxDetached<T>> {
+    /// Creates a cell named by its output type alone, boxing the initializer.
+    pub fn new_boxed(f: impl 'static + Future<Output = T>) -> Self {
+        AsyncLazy::new(LocalBoxDetached::new(f))
+    }
+}
+
+impl<R: AsyncRawFused, S: Spawner<Fu>, Fu: Future> AsyncLazy<R, SpawnOnPoll<S, Fu>>
+where
+    Fu::Output: 'static + Send,
+{
+    /// Creates a cell whose initializer is handed to `spawner` by the first call to
+    /// [`get`](Self::get), rather than being polled by the callers.
+    pub fn new_spawned(spawner: S, f: Fu) -> Self {
+        AsyncLazy::new(SpawnOnPoll::new(spawner, f))
+    }
+}
+
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static + Send + Debug, I> Debug
+    for AsyncLazy<R, F, I>
+where
+    I: FnOnce() -> F,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut d = f.debug_tuple("AsyncLazy");
+        match self.try_get_checked() {
+            Ok(Some(x)) => d.field(x),
+            Ok(None) => d.field(&format_args!("<uninit>")),
+            Err(_) => d.field(&format_args!("<poisoned>")),
+        };
+        d.finish()
+    }
 }
 
-impl<R: AsyncRawFused, F, T: 'static + Send> AsyncLazy<R, F>
+impl<R: AsyncRawFused, F, T: 'static + Send, I> AsyncLazy<R, F, I>
 where
     F: Send + Unpin + DetachedFuture<Output = T>,
+    I: Send + FnOnce() -> F,
 {
     fn get_is_send(&self) -> impl Send + Future<Output = &T>
     where
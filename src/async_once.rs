@@ -1,23 +1,31 @@
-use crate::async_fused::{AsyncFused, AsyncFusedEntry, AsyncFusedGuard};
+use crate::async_fused::{AsyncFused, AsyncFusedEntry, AsyncFusedGuard, PoisonPolicy};
 // use crate::detached::{detached, Detached};
-use std::cell::UnsafeCell;
-use std::fmt::{Debug, Formatter};
-use std::future::Future;
-use std::marker::{PhantomData, Unsize};
-use std::mem;
-use std::mem::MaybeUninit;
-use std::panic::{RefUnwindSafe, UnwindSafe};
-use std::pin::Pin;
-use std::sync::{PoisonError, TryLockError};
-use std::thread::panicking;
+use crate::raw::panicking;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::fmt::{Debug, Formatter};
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
+use core::mem;
+use core::mem::MaybeUninit;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use tokio::time::{timeout, timeout_at, Instant};
 // use safe_once::cell::OnceCell;
-use crate::detached::DetachedFuture;
-use crate::raw::{AsyncRawFused, RawOnceState};
-use crate::sync::AsyncOnceLock;
+#[cfg(feature = "std")]
+use crate::blocking::block_on;
+use crate::detached::{DetachedFuture, Spawner};
+use crate::error::Error;
+use crate::raw::{AsyncRawFused, RawOnceState, WriteScope};
 use crate::thunk::OptionThunk;
 
 pub struct AsyncOnce<R: AsyncRawFused, F: DetachedFuture> {
     fused: AsyncFused<R, OptionThunk<F::Output, F>>,
+    cancel_when_unused: bool,
 }
 
 pub enum AsyncOnceEntry<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> {
@@ -25,52 +33,130 @@ pub enum AsyncOnceEntry<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output =
     Occupied(AsyncOnceOccupied<'a, R, F, T>),
 }
 
+/// What [`AsyncOnce::get_or_init_deadline`] does with the cell when the deadline elapses.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeadlineAction {
+    /// Drop the initializer and unlock the cell, so that the next caller starts a new one.
+    Unlock,
+    /// Drop the initializer and poison the cell.
+    Poison,
+}
+
 pub struct AsyncOnceVacant<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> {
     guard: AsyncFusedGuard<'a, R, OptionThunk<T, F>>,
+    cancel_when_unused: bool,
 }
 
-pub type AsyncOnceOccupied<'a, R, F, T> = <() as AsyncOnceOccupiedTrait>::Fut<'a, R, F, T>;
+/// The write guard of an initializer in progress. If dropped early while nobody else is waiting
+/// and `cancel_when_unused` is set, the initializer is dropped too.
+struct InitGuard<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> {
+    guard: Option<AsyncFusedGuard<'a, R, OptionThunk<T, F>>>,
+    cancel_when_unused: bool,
+}
 
-pub trait AsyncOnceOccupiedTrait {
-    type Fut<'a, R: AsyncRawFused, F: 'a + Unpin + DetachedFuture<Output = T>, T: 'static>: 'a
-        + Future<Output = &'a T>
-    where
-        Self: 'a,
-        F: 'a;
-    fn async_once_occupied<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T>(
-        entry: AsyncFusedEntry<'a, R, OptionThunk<T, F>>,
-    ) -> AsyncOnceOccupied<'a, R, F, T>;
-}
-
-impl AsyncOnceOccupiedTrait for () {
-    type Fut<'a, R: AsyncRawFused, F: 'a + Unpin + DetachedFuture<Output = T>, T: 'static> =
-        impl 'a + Future<Output = &'a T>;
-    fn async_once_occupied<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T>(
-        entry: AsyncFusedEntry<'a, R, OptionThunk<T, F>>,
-    ) -> AsyncOnceOccupied<'a, R, F, T> {
-        async move {
-            match entry {
-                AsyncFusedEntry::Write(mut w) => {
-                    w.force().await;
-                    let w = w.fuse();
-                    w.get().unwrap()
-                }
-                AsyncFusedEntry::Read(r) => r.get().unwrap(),
+impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> InitGuard<'a, R, F, T> {
+    fn new(mut guard: AsyncFusedGuard<'a, R, OptionThunk<T, F>>, cancel_when_unused: bool) -> Self {
+        guard.begin_init();
+        InitGuard {
+            guard: Some(guard),
+            cancel_when_unused,
+        }
+    }
+    fn guard(&mut self) -> &mut AsyncFusedGuard<'a, R, OptionThunk<T, F>> {
+        self.guard.as_mut().unwrap()
+    }
+    /// Returns the guard once the initializer has finished or been given up on.
+    fn into_inner(mut self) -> AsyncFusedGuard<'a, R, OptionThunk<T, F>> {
+        let mut guard = self.guard.take().unwrap();
+        guard.end_init();
+        guard
+    }
+}
+
+impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> Drop
+    for InitGuard<'a, R, F, T>
+{
+    fn drop(&mut self) {
+        if let Some(mut guard) = self.guard.take() {
+            if self.cancel_when_unused && !panicking() && guard.waiters() == 0 {
+                guard.take();
+                guard.end_init();
             }
         }
     }
 }
 
+/// Waits for the cell's initializer to finish, driving it if this caller holds the write lock.
+pub struct AsyncOnceOccupied<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> {
+    state: OccupiedState<'a, R, F, T>,
+}
+
+enum OccupiedState<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> {
+    Write(InitGuard<'a, R, F, T>),
+    Read(&'a T),
+    Done,
+}
+
+impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T>
+    AsyncOnceOccupied<'a, R, F, T>
+{
+    fn new(entry: AsyncFusedEntry<'a, R, OptionThunk<T, F>>, cancel_when_unused: bool) -> Self {
+        let state = match entry {
+            AsyncFusedEntry::Write(w) => {
+                OccupiedState::Write(InitGuard::new(w, cancel_when_unused))
+            }
+            AsyncFusedEntry::Read(r) => OccupiedState::Read(r.get().unwrap()),
+        };
+        AsyncOnceOccupied { state }
+    }
+}
+
+impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> Unpin
+    for AsyncOnceOccupied<'a, R, F, T>
+{
+}
+
+impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T> Future
+    for AsyncOnceOccupied<'a, R, F, T>
+{
+    type Output = &'a T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        match &mut this.state {
+            OccupiedState::Write(w) => {
+                let scope = w.guard().scope_id();
+                let force = poll_fn(|cx| w.guard().poll_force(cx));
+                ready!(Pin::new(&mut WriteScope::new(scope, force)).poll(cx));
+                let OccupiedState::Write(w) = mem::replace(&mut this.state, OccupiedState::Done)
+                else {
+                    unreachable!()
+                };
+                Poll::Ready(w.into_inner().fuse().get().unwrap())
+            }
+            OccupiedState::Read(r) => Poll::Ready(*r),
+            OccupiedState::Done => panic!("polled after completion"),
+        }
+    }
+}
+
 impl<'a, R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static>
     AsyncOnceVacant<'a, R, F, T>
 {
-    pub fn start(mut self, f: F) -> AsyncOnceOccupied<'a, R, F, T> {
-        self.guard.start(f);
-        <()>::async_once_occupied(AsyncFusedEntry::Write(self.guard))
+    /// Fills the cell with `value` directly, without running an initializer.
+    pub fn insert(mut self, value: T) -> &'a T {
+        *self.guard = OptionThunk::new_value(value);
+        self.guard.fuse().get().unwrap()
     }
-    pub fn start_detached(mut self, f: F) -> AsyncOnceOccupied<'a, R, F, T> {
+    /// Like [`start`](Self::start), but builds the initializer inside this write scope, so that
+    /// an initializer spawned by `f` reports [`Error::Cycle`] if it waits for this cell.
+    fn start_with(self, f: impl FnOnce() -> F) -> AsyncOnceOccupied<'a, R, F, T> {
+        let f = self.guard.scope_id().enter(f);
+        self.start(f)
+    }
+    pub fn start(mut self, f: F) -> AsyncOnceOccupied<'a, R, F, T> {
         self.guard.start(f);
-        <()>::async_once_occupied(AsyncFusedEntry::Write(self.guard))
+        AsyncOnceOccupied::new(AsyncFusedEntry::Write(self.guard), self.cancel_when_unused)
     }
 }
 
@@ -78,14 +164,78 @@ impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> AsyncO
     pub const fn new() -> Self {
         AsyncOnce {
             fused: AsyncFused::new(OptionThunk::new()),
+            cancel_when_unused: false,
+        }
+    }
+    /// Creates a cell whose initializers are subject to `policy` when they panic or are
+    /// cancelled. A retried initializer is started afresh by the next caller.
+    pub const fn new_with_policy(policy: PoisonPolicy) -> Self {
+        AsyncOnce {
+            fused: AsyncFused::new_with_policy(OptionThunk::new(), policy),
+            cancel_when_unused: false,
+        }
+    }
+    /// Drops an in-progress initializer, resetting the cell, as soon as no caller is waiting
+    /// for it anymore. By default a cancelled initializer is resumed by the next caller.
+    pub const fn new_cancel_when_unused() -> Self {
+        AsyncOnce {
+            fused: AsyncFused::new(OptionThunk::new()),
+            cancel_when_unused: true,
+        }
+    }
+    /// Creates a cell that is already initialized with `value`.
+    // There is no `From<T>` impl to go with this: `T` is only named as `F::Output`, and an
+    // `impl From<F::Output> for AsyncOnce<R, F>` overlaps core's `impl<T> From<T> for T`,
+    // because nothing rules out an initializer whose output is the cell itself.
+    pub const fn new_with_value(value: T) -> Self {
+        AsyncOnce {
+            fused: AsyncFused::new_read(OptionThunk::new_value(value)),
+            cancel_when_unused: false,
         }
     }
     pub const fn poisoned() -> Self {
         AsyncOnce {
             fused: AsyncFused::poisoned(OptionThunk::new()),
+            cancel_when_unused: false,
+        }
+    }
+    pub fn is_poisoned(&self) -> bool {
+        self.fused.is_poisoned()
+    }
+    /// Recovers from a panicking initializer by discarding its future, so that the next caller
+    /// starts a new one.
+    pub async fn clear_poison(&self) {
+        if let Err(mut guard) = self.fused.write_recover_guard().await {
+            guard.take();
         }
     }
-    pub fn try_lock(&self) -> Option<AsyncOnceEntry<R, F, T>> {
+    /// Returns the value if it has been initialized.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.fused.get_mut().get_mut()
+    }
+    /// Takes the value out, leaving the cell uninitialized. Any in-progress initializer is
+    /// dropped and poison is cleared.
+    pub fn take(&mut self) -> Option<T> {
+        let value = self.fused.get_mut().take();
+        self.fused.reset();
+        value
+    }
+    pub fn into_inner(self) -> Option<T> {
+        self.fused.into_inner().into_inner()
+    }
+    /// Like [`try_get`](Self::try_get), but returns [`Error::Poisoned`] for a poisoned cell
+    /// rather than `None`.
+    pub fn try_get_checked(&self) -> Result<Option<&T>, Error> {
+        Ok(self.fused.try_read_checked()?.and_then(|x| x.get()))
+    }
+    /// Returns the value if it has been initialized, without waiting or locking.
+    pub fn try_get(&self) -> Option<&T> {
+        self.try_get_checked().ok()?
+    }
+    pub fn try_lock_checked(&self) -> Result<AsyncOnceEntry<'_, R, F, T>, Error> {
+        Ok(self.raw_lock(self.fused.try_write_checked()?))
+    }
+    pub fn try_lock(&self) -> Option<AsyncOnceEntry<'_, R, F, T>> {
         Some(self.raw_lock(self.fused.try_write()?))
     }
     fn raw_lock<'a>(
@@ -95,35 +245,280 @@ impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> AsyncO
         match raw {
             AsyncFusedEntry::Write(w) => {
                 if w.started() {
-                    AsyncOnceEntry::Occupied(<()>::async_once_occupied(AsyncFusedEntry::Write(w)))
+                    AsyncOnceEntry::Occupied(AsyncOnceOccupied::new(
+                        AsyncFusedEntry::Write(w),
+                        self.cancel_when_unused,
+                    ))
                 } else {
-                    AsyncOnceEntry::Vacant(AsyncOnceVacant { guard: w })
+                    AsyncOnceEntry::Vacant(AsyncOnceVacant {
+                        guard: w,
+                        cancel_when_unused: self.cancel_when_unused,
+                    })
                 }
             }
-            AsyncFusedEntry::Read(r) => {
-                AsyncOnceEntry::Occupied(<()>::async_once_occupied(AsyncFusedEntry::Read(r)))
+            AsyncFusedEntry::Read(r) => AsyncOnceEntry::Occupied(AsyncOnceOccupied::new(
+                AsyncFusedEntry::Read(r),
+                self.cancel_when_unused,
+            )),
+        }
+    }
+    pub async fn lock_checked(&self) -> Result<AsyncOnceEntry<'_, R, F, T>, Error> {
+        Ok(self.raw_lock(self.fused.write_checked().await?))
+    }
+    pub async fn lock(&self) -> AsyncOnceEntry<'_, R, F, T> {
+        self.lock_checked().await.unwrap()
+    }
+    /// Fills the cell with `value` if it is vacant, waiting for the lock if necessary. If the
+    /// cell is already initialized, or an initializer has been started, `value` is returned.
+    ///
+    /// # Panics
+    /// Like [`lock`](Self::lock), if the cell is poisoned or the caller is its own initializer.
+    pub async fn set(&self, value: T) -> Result<(), T> {
+        match self.fused.write_checked().await.unwrap() {
+            AsyncFusedEntry::Write(w) if !w.started() => {
+                AsyncOnceVacant {
+                    guard: w,
+                    cancel_when_unused: self.cancel_when_unused,
+                }
+                .insert(value);
+                Ok(())
             }
+            AsyncFusedEntry::Write(_) | AsyncFusedEntry::Read(_) => Err(value),
         }
     }
-    pub async fn lock(&self) -> AsyncOnceEntry<R, F, T> {
-        self.raw_lock(self.fused.write().await)
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but the value is computed synchronously
+    /// by `f` rather than by a future.
+    pub async fn get_or_init_with(&self, f: impl FnOnce() -> T) -> &T {
+        match self.lock().await {
+            AsyncOnceEntry::Vacant(x) => x.insert(f()),
+            AsyncOnceEntry::Occupied(x) => x.await,
+        }
     }
     pub async fn get_or_init_fn(&self, f: impl FnOnce() -> F) -> &T {
         let occupied = match self.lock().await {
-            AsyncOnceEntry::Vacant(x) => x.start(f()),
+            AsyncOnceEntry::Vacant(x) => x.start_with(f),
             AsyncOnceEntry::Occupied(x) => x,
         };
         occupied.await
     }
-    pub async fn get_or_init<'a>(&'a self, f: F) -> &'a T {
+    pub async fn get_or_init(&self, f: F) -> &T {
         self.get_or_init_fn(|| f).await
     }
-    pub async fn get_or_init_detached(&self, f: impl FnOnce() -> F) -> &T {
-        let occupied = match self.lock().await {
-            AsyncOnceEntry::Vacant(x) => x.start_detached(f()),
-            AsyncOnceEntry::Occupied(x) => x,
+    /// Blocks the current thread until the value is ready, driving the initializer inline if no
+    /// other task is.
+    ///
+    /// # Panics
+    /// With the `tokio-rt` feature, if the value is not ready and this is called from inside a
+    /// tokio runtime.
+    #[cfg(feature = "std")]
+    pub fn get_or_init_blocking(&self, f: impl FnOnce() -> F) -> &T {
+        match self.try_get() {
+            Some(x) => x,
+            None => match self.raw_lock(self.fused.write_checked_blocking().unwrap()) {
+                AsyncOnceEntry::Vacant(x) => block_on(x.start_with(f)),
+                AsyncOnceEntry::Occupied(x) => block_on(x),
+            },
+        }
+    }
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but gives up with [`Error::TimedOut`]
+    /// after `duration`, and reports a poisoned cell or a cycle as an error.
+    ///
+    /// Giving up only stops this caller. A spawned initializer, e.g. from
+    /// [`get_or_spawn`](Self::get_or_spawn), keeps running. One that this caller was polling
+    /// inline pauses until the next caller resumes it, unless the cell was created with
+    /// [`new_cancel_when_unused`](Self::new_cancel_when_unused) or
+    /// [`PoisonPolicy::PoisonOnCancel`].
+    #[cfg(feature = "std")]
+    pub async fn get_or_init_timeout(
+        &self,
+        f: impl FnOnce() -> F,
+        duration: Duration,
+    ) -> Result<&T, Error> {
+        let get = async {
+            Ok(match self.lock_checked().await? {
+                AsyncOnceEntry::Vacant(x) => x.start_with(f).await,
+                AsyncOnceEntry::Occupied(x) => x.await,
+            })
         };
-        occupied.await
+        timeout(duration, get).await.map_err(|_| Error::TimedOut)?
+    }
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but if the value is not available by
+    /// `deadline`, the in-progress initializer is dropped and the cell is unlocked or poisoned
+    /// according to `expired`.
+    #[cfg(feature = "std")]
+    pub async fn get_or_init_deadline(
+        &self,
+        f: impl FnOnce() -> F,
+        deadline: Instant,
+        expired: DeadlineAction,
+    ) -> Result<&T, Error> {
+        let entry = timeout_at(deadline, self.fused.write_checked())
+            .await
+            .map_err(|_| Error::TimedOut)??;
+        let w = match entry {
+            AsyncFusedEntry::Write(w) => w,
+            AsyncFusedEntry::Read(r) => return Ok(r.get().unwrap()),
+        };
+        let mut w = InitGuard::new(w, self.cancel_when_unused);
+        let scope = w.guard().scope_id();
+        if !w.guard().started() {
+            w.guard().start(scope.enter(f));
+        }
+        let done = timeout_at(deadline, WriteScope::new(scope, w.guard().force()))
+            .await
+            .is_ok();
+        let mut w = w.into_inner();
+        if done {
+            return Ok(w.fuse().get().unwrap());
+        }
+        w.take();
+        match expired {
+            DeadlineAction::Unlock => drop(w),
+            DeadlineAction::Poison => w.poison(),
+        }
+        Err(Error::TimedOut)
+    }
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but `f` is started on `spawner` so that
+    /// it makes progress without being polled by a caller. If every caller stops waiting, the
+    /// task keeps running only while its handle stays in the cell: once the cell is dropped or
+    /// [`take`](Self::take)n, a handle such as `JoinTransparent` cancels it. Use
+    /// [`get_or_spawn_detached`](Self::get_or_spawn_detached) to keep it alive regardless.
+    pub async fn get_or_spawn<S, Fu>(&self, spawner: S, f: impl FnOnce() -> Fu) -> &T
+    where
+        S: Spawner<Fu, Handle = F>,
+        Fu: Future<Output = T>,
+    {
+        self.get_or_init_fn(|| spawner.spawn(f())).await
+    }
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but a new initializer is asked to
+    /// [`keep_alive`](DetachedFuture::keep_alive), so that its spawned work is not cancelled
+    /// when the cell is dropped or [`take`](Self::take)n. The value only reaches the cell when
+    /// a caller polls it again; use [`get_or_spawn_detached`](Self::get_or_spawn_detached) to
+    /// store it without one.
+    pub async fn get_or_init_detached(&self, f: impl FnOnce() -> F) -> &T {
+        self.get_or_init_fn(|| {
+            let mut f = f();
+            Pin::new(&mut f).keep_alive();
+            f
+        })
+        .await
+    }
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but the initializer is driven by a
+    /// [`DetachedInit`] task on `spawner`, which holds its own reference to the cell. The
+    /// initializer therefore runs to completion and stores its value in the cell even if every
+    /// caller stops waiting for it.
+    ///
+    /// # Panics
+    /// If the detached initializer panicked, poisoning the cell.
+    #[cfg(target_has_atomic = "ptr")]
+    pub async fn get_or_spawn_detached<S>(self: &Arc<Self>, spawner: S, f: impl FnOnce() -> F) -> &T
+    where
+        S: Spawner<DetachedInit<R, F, T>>,
+        R: 'static,
+        F: 'static,
+    {
+        if let AsyncFusedEntry::Write(mut w) = self.fused.write().await {
+            if !w.started() {
+                let mut f = w.scope_id().enter(f);
+                Pin::new(&mut f).keep_alive();
+                w.start(f);
+            }
+            w.begin_init();
+            // The guard only borrows the cell, which `DetachedInit` keeps alive in `_cell`.
+            let guard = unsafe {
+                mem::transmute::<
+                    AsyncFusedGuard<'_, R, OptionThunk<T, F>>,
+                    AsyncFusedGuard<'static, R, OptionThunk<T, F>>,
+                >(w)
+            };
+            let mut handle = spawner.spawn(DetachedInit {
+                guard: Some(guard),
+                _cell: self.clone(),
+            });
+            Pin::new(&mut handle).keep_alive();
+        }
+        match self.fused.read_checked().await {
+            Ok(Some(x)) => x.get().unwrap(),
+            // The task was dropped before it finished, e.g. by a runtime shutting down.
+            Ok(None) => self.get_or_init_fn(|| panic!("{}", Error::Cancelled)).await,
+            Err(e) => panic!("detached initializer failed: {}", e),
+        }
+    }
+}
+
+/// A task that drives the initializer of an [`AsyncOnce`] and stores its value, started by
+/// [`get_or_spawn_detached`](AsyncOnce::get_or_spawn_detached).
+#[cfg(target_has_atomic = "ptr")]
+pub struct DetachedInit<
+    R: 'static + AsyncRawFused,
+    F: 'static + Unpin + DetachedFuture<Output = T>,
+    T: 'static,
+> {
+    // Declared first so that it is dropped before the cell it borrows.
+    guard: Option<AsyncFusedGuard<'static, R, OptionThunk<T, F>>>,
+    _cell: Arc<AsyncOnce<R, F>>,
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<R: 'static + AsyncRawFused, F: 'static + Unpin + DetachedFuture<Output = T>, T: 'static> Unpin
+    for DetachedInit<R, F, T>
+{
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<R: 'static + AsyncRawFused, F: 'static + Unpin + DetachedFuture<Output = T>, T: 'static> Future
+    for DetachedInit<R, F, T>
+{
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let guard = self.guard.as_mut().expect("polled after completion");
+        let scope = guard.scope_id();
+        let force = poll_fn(|cx| guard.poll_force(cx));
+        ready!(Pin::new(&mut WriteScope::new(scope, force)).poll(cx));
+        self.guard.take().unwrap().fuse();
+        Poll::Ready(())
+    }
+}
+
+impl<
+        R: AsyncRawFused,
+        F: Unpin + DetachedFuture<Output = Result<T, E>>,
+        T: 'static,
+        E: 'static,
+    > AsyncOnce<R, F>
+{
+    /// Like [`get_or_init_fn`](Self::get_or_init_fn), but an `Err` output is returned to the
+    /// caller that observed it instead of being cached. The cell is left vacant so that the
+    /// next caller retries.
+    pub async fn get_or_try_init_fn(&self, f: impl FnOnce() -> F) -> Result<&T, E> {
+        match self.fused.write().await {
+            AsyncFusedEntry::Write(w) => {
+                let mut w = InitGuard::new(w, self.cancel_when_unused);
+                let scope = w.guard().scope_id();
+                if !w.guard().started() {
+                    w.guard().start(scope.enter(f));
+                }
+                let failed = WriteScope::new(scope, w.guard().force()).await.is_err();
+                let mut w = w.into_inner();
+                if failed {
+                    let Some(Err(e)) = w.take() else {
+                        unreachable!()
+                    };
+                    return Err(e);
+                }
+                match w.fuse().get() {
+                    Some(Ok(x)) => Ok(x),
+                    _ => unreachable!(),
+                }
+            }
+            AsyncFusedEntry::Read(r) => match r.get() {
+                Some(Ok(x)) => Ok(x),
+                _ => unreachable!(),
+            },
+        }
+    }
+    pub async fn get_or_try_init(&self, f: F) -> Result<&T, E> {
+        self.get_or_try_init_fn(|| f).await
     }
 }
 
@@ -135,7 +530,22 @@ impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> Defaul
     }
 }
 
-#[cfg(test)]
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture> Debug for AsyncOnce<R, F>
+where
+    F::Output: 'static + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut d = f.debug_tuple("AsyncOnce");
+        match self.try_get_checked() {
+            Ok(Some(x)) => d.field(x),
+            Ok(None) => d.field(&format_args!("<uninit>")),
+            Err(_) => d.field(&format_args!("<poisoned>")),
+        };
+        d.finish()
+    }
+}
+
+#[cfg(all(test, feature = "tokio-rt"))]
 mod test {
     use crate::detached::{spawn_transparent, JoinTransparent};
     use crate::sync::AsyncOnceLock;
@@ -146,17 +556,31 @@ mod test {
         assert_eq!(*foo.get_or_init(spawn_transparent(async { 2 })).await, 2);
         assert_eq!(*foo.get_or_init(spawn_transparent(async { 3 })).await, 2);
     }
-}
 
-impl<R: AsyncRawFused, F: Unpin + DetachedFuture> Debug for AsyncOnce<R, F>
-where
-    F::Output: 'static,
-{
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.try_lock() {
-            None => write!(f, "locked"),
-            Some(AsyncOnceEntry::Vacant(v)) => write!(f, "vacant"),
-            Some(AsyncOnceEntry::Occupied(v)) => write!(f, "occupied"),
-        }
+    #[tokio::test]
+    async fn test_get_or_try_init() {
+        let foo = AsyncOnceLock::<JoinTransparent<Result<usize, &'static str>>>::new();
+        assert_eq!(
+            foo.get_or_try_init(spawn_transparent(async { Err("retry") }))
+                .await,
+            Err("retry")
+        );
+        assert_eq!(
+            foo.get_or_try_init(spawn_transparent(async { Ok(2) }))
+                .await,
+            Ok(&2)
+        );
+        assert_eq!(foo.get_or_try_init_fn(|| unreachable!()).await, Ok(&2));
+    }
+
+    #[tokio::test]
+    async fn test_take() {
+        let mut foo = AsyncOnceLock::<JoinTransparent<usize>>::new();
+        assert_eq!(foo.get_mut(), None);
+        foo.get_or_init(spawn_transparent(async { 2 })).await;
+        *foo.get_mut().unwrap() += 1;
+        assert_eq!(foo.take(), Some(3));
+        assert_eq!(*foo.get_or_init(spawn_transparent(async { 4 })).await, 4);
+        assert_eq!(foo.into_inner(), Some(4));
     }
 }
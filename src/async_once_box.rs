@@ -0,0 +1,95 @@
+use crate::async_once::AsyncOnce;
+use crate::detached::{BoxDetached, DetachedFuture, LocalBoxDetached};
+use crate::raw::AsyncRawFused;
+use crate::raw::AsyncRawFusedSync;
+use core::fmt::{Debug, Formatter};
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+
+/// An [`AsyncOnce`] that boxes its initializer, so that it is named by its output type alone
+/// and any future producing that output can be passed to [`get_or_init`](Self::get_or_init).
+///
+/// The remaining [`AsyncOnce`] methods are available through `Deref`.
+pub struct AsyncOnceBox<R: AsyncRawFused, B: DetachedFuture> {
+    once: AsyncOnce<R, B>,
+}
+
+impl<R: AsyncRawFused, B: Unpin + DetachedFuture<Output = T>, T: 'static> AsyncOnceBox<R, B> {
+    pub const fn new() -> Self {
+        AsyncOnceBox {
+            once: AsyncOnce::new(),
+        }
+    }
+    pub const fn new_with_value(value: T) -> Self {
+        AsyncOnceBox {
+            once: AsyncOnce::new_with_value(value),
+        }
+    }
+    pub fn into_inner(self) -> Option<T> {
+        self.once.into_inner()
+    }
+}
+
+impl<R: AsyncRawFused, T: 'static> AsyncOnceBox<R, BoxDetached<T>> {
+    pub async fn get_or_init(&self, f: impl 'static + Send + Future<Output = T>) -> &T {
+        self.once.get_or_init_fn(|| BoxDetached::new(f)).await
+    }
+    pub async fn get_or_init_fn<Fu>(&self, f: impl FnOnce() -> Fu) -> &T
+    where
+        Fu: 'static + Send + Future<Output = T>,
+    {
+        self.once.get_or_init_fn(|| BoxDetached::new(f())).await
+    }
+    fn get_or_init_is_send<Fu>(&self, f: Fu) -> impl '_ + Send + Future<Output = &'_ T>
+    where
+        R: AsyncRawFusedSync,
+        Fu: 'static + Send + Future<Output = T>,
+        T: Send + Sync,
+    {
+        self.get_or_init(f)
+    }
+}
+
+impl<R: AsyncRawFused, T: 'static> AsyncOnceBox<R, LocalBoxDetached<T>> {
+    pub async fn get_or_init(&self, f: impl 'static + Future<Output = T>) -> &T {
+        self.once.get_or_init_fn(|| LocalBoxDetached::new(f)).await
+    }
+    pub async fn get_or_init_fn<Fu>(&self, f: impl FnOnce() -> Fu) -> &T
+    where
+        Fu: 'static + Future<Output = T>,
+    {
+        self.once
+            .get_or_init_fn(|| LocalBoxDetached::new(f()))
+            .await
+    }
+}
+
+impl<R: AsyncRawFused, B: DetachedFuture> Deref for AsyncOnceBox<R, B> {
+    type Target = AsyncOnce<R, B>;
+    fn deref(&self) -> &Self::Target {
+        &self.once
+    }
+}
+
+impl<R: AsyncRawFused, B: DetachedFuture> DerefMut for AsyncOnceBox<R, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.once
+    }
+}
+
+impl<R: AsyncRawFused, B: Unpin + DetachedFuture<Output = T>, T: 'static> Default
+    for AsyncOnceBox<R, B>
+{
+    fn default() -> Self {
+        AsyncOnceBox::new()
+    }
+}
+
+impl<R: AsyncRawFused, B: Unpin + DetachedFuture> Debug for AsyncOnceBox<R, B>
+where
+    B::Output: 'static + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.once.fmt(f)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::async_once::AsyncOnce;
+use crate::detached::DetachedFuture;
+use crate::raw::AsyncRawFused;
+use crate::raw::AsyncRawFusedSync;
+use parking_lot::{const_mutex, Mutex};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// An [`AsyncOnce`] that can be [`reset`](Self::reset) through a shared reference, e.g. to
+/// drop a cached client once its credentials expire.
+///
+/// Each generation of the value lives in its own reference-counted [`AsyncOnce`]. Readers get an
+/// [`AsyncRefreshRef`] that keeps their generation alive, so resetting never invalidates a value
+/// that is still in use.
+pub struct AsyncRefresh<R: AsyncRawFused, F: DetachedFuture> {
+    current: Mutex<Option<Arc<AsyncOnce<R, F>>>>,
+}
+
+/// A handle to one generation of the value in an [`AsyncRefresh`].
+pub struct AsyncRefreshRef<R: AsyncRawFused, F: DetachedFuture> {
+    once: Arc<AsyncOnce<R, F>>,
+}
+
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> AsyncRefresh<R, F> {
+    pub const fn new() -> Self {
+        AsyncRefresh {
+            current: const_mutex(None),
+        }
+    }
+    fn current(&self) -> Arc<AsyncOnce<R, F>> {
+        self.current
+            .lock()
+            .get_or_insert_with(Default::default)
+            .clone()
+    }
+    pub async fn get_or_init_fn(&self, f: impl FnOnce() -> F) -> AsyncRefreshRef<R, F> {
+        let once = self.current();
+        once.get_or_init_fn(f).await;
+        AsyncRefreshRef { once }
+    }
+    pub async fn get_or_init(&self, f: F) -> AsyncRefreshRef<R, F> {
+        self.get_or_init_fn(|| f).await
+    }
+    /// Discards the current generation, so that the next caller starts a new initializer.
+    /// Existing [`AsyncRefreshRef`]s keep the old value alive until they are dropped.
+    pub fn reset(&self) {
+        self.current.lock().take();
+    }
+}
+
+impl<R: AsyncRawFused, F, T: 'static> AsyncRefresh<R, F>
+where
+    F: Send + Unpin + DetachedFuture<Output = T>,
+{
+    fn get_or_init_is_send(&self, f: F) -> impl '_ + Send + Future<Output = AsyncRefreshRef<R, F>>
+    where
+        R: AsyncRawFusedSync,
+        F: Sync,
+        T: Send + Sync,
+    {
+        self.get_or_init(f)
+    }
+}
+
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> Default
+    for AsyncRefresh<R, F>
+{
+    fn default() -> Self {
+        AsyncRefresh::new()
+    }
+}
+
+impl<R: AsyncRawFused, F: DetachedFuture> Debug for AsyncRefresh<R, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncRefresh").finish_non_exhaustive()
+    }
+}
+
+impl<R: AsyncRawFused, F: Unpin + DetachedFuture<Output = T>, T: 'static> Deref
+    for AsyncRefreshRef<R, F>
+{
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.once.try_get().unwrap()
+    }
+}
+
+impl<R: AsyncRawFused, F: DetachedFuture> Clone for AsyncRefreshRef<R, F> {
+    fn clone(&self) -> Self {
+        AsyncRefreshRef {
+            once: self.once.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio-rt"))]
+mod test {
+    use crate::detached::{spawn_transparent, JoinTransparent};
+    use crate::sync::AsyncRefreshLock;
+
+    #[tokio::test]
+    async fn test_refresh() {
+        static CLIENT: AsyncRefreshLock<JoinTransparent<usize>> = AsyncRefreshLock::new();
+        let first = CLIENT.get_or_init(spawn_transparent(async { 1 })).await;
+        assert_eq!(*CLIENT.get_or_init(spawn_transparent(async { 2 })).await, 1);
+        CLIENT.reset();
+        let second = CLIENT.get_or_init(spawn_transparent(async { 3 })).await;
+        assert_eq!((*first, *second), (1, 3));
+    }
+}
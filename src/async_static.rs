@@ -1,11 +1,19 @@
 use crate::async_fused::{AsyncFused, AsyncFusedEntry, AsyncFusedGuard};
-// use crate::const_box::{ConstBox, ConstBoxFuture};
-use crate::detached::detached;
-use crate::raw::AsyncRawFused;
-// use crate::sync::AsyncStaticLock;
+use crate::const_box::ConstBoxFuture;
+use crate::error::Error;
+use crate::raw::{AsyncRawFused, WriteScope};
 use crate::thunk::Thunk;
-use std::future::Future;
+use core::future::Future;
 
+/// A lazily initialized value that can be stored directly in a `static`. The initializer is a
+/// non-capturing closure returning a future; it is called and boxed on the first
+/// [`get`](Self::get), and the future is resumed by the next caller if that task is cancelled.
+///
+/// [`new`](Self::new) takes a function rather than the future itself: a future cannot be boxed,
+/// and so have its type erased, during const evaluation, while a function pointer can be called
+/// and boxed on first use. The future is polled inline by whichever caller holds the lock rather
+/// than spawned, so that the cell does not depend on a runtime; to run it in the background,
+/// return a spawned handle, e.g. `|| spawn_transparent(init())`.
 pub struct AsyncStatic<R: AsyncRawFused, T> {
     fused: AsyncFused<R, Thunk<T, ConstBoxFuture<T>>>,
 }
@@ -14,36 +22,64 @@ impl<R: Send + Sync + AsyncRawFused, T: 'static + Sync + Send> AsyncStatic<R, T>
 where
     R::GuardMarker: Send,
 {
-    pub const fn new<Fu>(fu: Fu) -> Self
+    pub const fn new<Fu>(init: fn() -> Fu) -> Self
     where
         Fu: 'static + Send + Future<Output = T>,
     {
         AsyncStatic {
-            fused: AsyncFused::new(Thunk::new(ConstBox::pin(async move { detached(fu).await }))),
+            fused: AsyncFused::new(Thunk::new(ConstBoxFuture::new(init))),
         }
     }
 
-    async fn lock(&self) -> AsyncFusedEntry<R, Thunk<T, ConstBoxFuture<T>>> {
-        self.fused.write().await
+    async fn lock(&self) -> Result<AsyncFusedEntry<'_, R, Thunk<T, ConstBoxFuture<T>>>, Error> {
+        self.fused.write_checked().await
     }
 
-    pub async fn get(&self) -> &T {
-        match self.lock().await {
+    pub async fn get_checked(&self) -> Result<&T, Error> {
+        Ok(match self.lock().await? {
             AsyncFusedEntry::Write(mut guard) => {
-                guard.get_or_init().await;
+                guard.begin_init();
+                WriteScope::new(guard.scope_id(), guard.get_or_init()).await;
                 guard.fuse().get().unwrap()
             }
             AsyncFusedEntry::Read(x) => x.get().unwrap(),
-        }
+        })
+    }
+
+    pub async fn get(&self) -> &T {
+        self.get_checked().await.unwrap()
     }
 }
 
 unsafe impl<R: Send + Sync + AsyncRawFused, T: Send + Sync> Sync for AsyncStatic<R, T> {}
 
-// #[tokio::test]
-// async fn test_static() {
-//     static FOO: AsyncStaticLock<usize> = AsyncStaticLock::new(async {
-//         return 2usize;
-//     });
-//     assert_eq!(FOO.get().await, &2usize);
-// }
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::sync::AsyncStaticLock;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::Relaxed;
+
+    #[tokio::test]
+    async fn test_static() {
+        static FOO: AsyncStaticLock<usize> = AsyncStaticLock::new(|| async { 2usize });
+        assert_eq!(FOO.get().await, &2usize);
+        assert_eq!(FOO.get().await, &2usize);
+    }
+
+    #[tokio::test]
+    async fn test_static_tasks() {
+        static INITS: AtomicUsize = AtomicUsize::new(0);
+        static FOO: AsyncStaticLock<String> = AsyncStaticLock::new(|| async {
+            INITS.fetch_add(1, Relaxed);
+            tokio::task::yield_now().await;
+            "foo".to_string()
+        });
+        let tasks: Vec<_> = (0..10)
+            .map(|_| tokio::spawn(async { FOO.get().await.len() }))
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 3);
+        }
+        assert_eq!(INITS.load(Relaxed), 1);
+    }
+}
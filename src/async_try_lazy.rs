@@ -0,0 +1,72 @@
+use crate::async_once::AsyncOnce;
+use crate::detached::DetachedFuture;
+use crate::raw::AsyncRawFused;
+use crate::raw::AsyncRawFusedSync;
+use core::future::Future;
+
+/// A lazily initialized value whose initializer may fail. Unlike
+/// [`AsyncLazy`](crate::async_lazy::AsyncLazy), the initializer is a function that builds a fresh
+/// future, so that a failed attempt can be retried by the next call to [`get`](Self::get).
+pub struct AsyncTryLazy<R: AsyncRawFused, F: DetachedFuture, I = fn() -> F> {
+    once: AsyncOnce<R, F>,
+    init: I,
+}
+
+impl<R: AsyncRawFused, F, T: 'static, E: 'static, I> AsyncTryLazy<R, F, I>
+where
+    F: Unpin + DetachedFuture<Output = Result<T, E>>,
+    I: Fn() -> F,
+{
+    pub const fn new(init: I) -> Self {
+        AsyncTryLazy {
+            once: AsyncOnce::new(),
+            init,
+        }
+    }
+
+    pub async fn get(&self) -> Result<&T, E> {
+        self.once.get_or_try_init_fn(&self.init).await
+    }
+}
+
+impl<R: AsyncRawFused, F, T: 'static + Send, E: 'static + Send, I> AsyncTryLazy<R, F, I>
+where
+    F: Send + Unpin + DetachedFuture<Output = Result<T, E>>,
+    I: Sync + Fn() -> F,
+{
+    fn get_is_send(&self) -> impl Send + Future<Output = Result<&T, E>>
+    where
+        R: AsyncRawFusedSync,
+        F: Sync,
+        T: Send + Sync,
+        E: Sync,
+    {
+        self.get()
+    }
+}
+
+#[cfg(all(test, feature = "tokio-rt"))]
+mod test {
+    use crate::detached::{spawn_transparent, JoinTransparent};
+    use crate::sync::AsyncTryLazyLock;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::Relaxed;
+
+    #[tokio::test]
+    async fn test_try_lazy() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+        static FOO: AsyncTryLazyLock<JoinTransparent<Result<usize, usize>>> =
+            AsyncTryLazyLock::new(|| {
+                spawn_transparent(async {
+                    match ATTEMPTS.fetch_add(1, Relaxed) {
+                        0 => Err(0),
+                        n => Ok(n),
+                    }
+                })
+            });
+        assert_eq!(FOO.get().await, Err(0));
+        assert_eq!(FOO.get().await, Ok(&1));
+        assert_eq!(FOO.get().await, Ok(&1));
+        assert_eq!(ATTEMPTS.load(Relaxed), 2);
+    }
+}
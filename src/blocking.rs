@@ -0,0 +1,20 @@
+use std::future::Future;
+
+/// Drives `fut` to completion on the current thread, parking it while the future is pending.
+///
+/// # Panics
+/// With the `tokio-rt` feature, if called from inside a tokio runtime, where parking the thread
+/// could stop the very task that would complete `fut`.
+pub(crate) fn block_on<Fu: Future>(fut: Fu) -> Fu::Output {
+    assert_not_in_runtime();
+    futures::executor::block_on(fut)
+}
+
+/// Panics if the current thread is running a tokio runtime, which must not be parked. Detecting
+/// a runtime needs tokio's `rt`, so without `tokio-rt` this checks nothing.
+pub(crate) fn assert_not_in_runtime() {
+    #[cfg(feature = "tokio-rt")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        panic!("cannot block on an async cell from inside a tokio runtime; await it instead");
+    }
+}
@@ -1,13 +1,15 @@
 use crate::cell::condvar::Condvar;
-use parking_lot::lock_api::GuardNoSend;
-use std::cell::{Cell, RefCell};
-use std::future::Future;
-use std::mem;
-use std::ptr::{null, null_mut};
-use std::sync::{PoisonError, TryLockError};
-use std::task::Waker;
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::mem;
+use core::ptr::{null, null_mut};
+use core::task::Waker;
+#[cfg(not(feature = "nightly"))]
+use futures::future::LocalBoxFuture;
 
-use crate::raw::{AsyncRawFused, RawOnceState};
+use crate::error::Error;
+use crate::raw::{in_write_scope, AsyncRawFused, GuardNoSend, RawOnceState};
+use alloc::boxed::Box;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 enum State {
@@ -21,10 +23,28 @@ enum State {
 pub struct AsyncRawFusedCell {
     state: Cell<State>,
     writers: Condvar,
+    readers: Condvar,
+    waiters: Cell<usize>,
+}
+
+/// Counts a caller of `write_checked` as waiting until it is dropped.
+struct Waiting<'a>(&'a Cell<usize>);
+
+impl<'a> Waiting<'a> {
+    fn new(waiters: &'a Cell<usize>) -> Self {
+        waiters.set(waiters.get() + 1);
+        Waiting(waiters)
+    }
+}
+
+impl<'a> Drop for Waiting<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
 }
 
 impl AsyncRawFusedCell {
-    pub async fn write_checked_impl(&self) -> Result<RawOnceState, TryLockError<()>> {
+    pub async fn write_checked_impl(&self) -> Result<RawOnceState, Error> {
         loop {
             match self.state.get() {
                 State::Unlocked => {
@@ -32,17 +52,34 @@ impl AsyncRawFusedCell {
                     return Ok(RawOnceState::Vacant);
                 }
                 State::Write => {
+                    if in_write_scope(self) {
+                        return Err(Error::Cycle);
+                    }
+                    let waiting = Waiting::new(&self.waiters);
                     self.writers.wait().await.consume();
                 }
                 State::Read => {
                     return Ok(RawOnceState::Occupied);
                 }
                 State::Poison => {
-                    return Err(TryLockError::Poisoned(PoisonError::new(())));
+                    return Err(Error::Poisoned);
                 }
             }
         }
     }
+    pub async fn read_checked_impl(&self) -> Result<RawOnceState, Error> {
+        loop {
+            match self.state.get() {
+                State::Write => {
+                    if in_write_scope(self) {
+                        return Err(Error::Cycle);
+                    }
+                    self.readers.wait().await.consume()
+                }
+                _ => return self.try_read_checked(),
+            }
+        }
+    }
 }
 
 unsafe impl AsyncRawFused for AsyncRawFusedCell {
@@ -50,63 +87,97 @@ unsafe impl AsyncRawFused for AsyncRawFusedCell {
     const UNLOCKED: Self = AsyncRawFusedCell {
         state: Cell::new(State::Unlocked),
         writers: Condvar::new(),
+        readers: Condvar::new(),
+        waiters: Cell::new(0),
     };
     const READ: Self = AsyncRawFusedCell {
         state: Cell::new(State::Read),
         writers: Condvar::new(),
+        readers: Condvar::new(),
+        waiters: Cell::new(0),
     };
     const POISON: Self = AsyncRawFusedCell {
         state: Cell::new(State::Poison),
         writers: Condvar::new(),
+        readers: Condvar::new(),
+        waiters: Cell::new(0),
     };
 
-    fn try_write_checked(&self) -> Result<Option<RawOnceState>, PoisonError<()>> {
+    fn try_write_checked(&self) -> Result<RawOnceState, Error> {
         match self.state.get() {
             State::Unlocked => {
                 self.state.set(State::Write);
-                Ok(Some(RawOnceState::Vacant))
+                Ok(RawOnceState::Vacant)
             }
-            State::Write => Ok(None),
-            State::Read => Ok(Some(RawOnceState::Occupied)),
-            State::Poison => Err(PoisonError::new(())),
+            State::Write => Err(Error::WouldBlock),
+            State::Read => Ok(RawOnceState::Occupied),
+            State::Poison => Err(Error::Poisoned),
         }
     }
 
-    fn try_read_checked(&self) -> Result<RawOnceState, PoisonError<()>> {
+    fn try_read_checked(&self) -> Result<RawOnceState, Error> {
         match self.state.get() {
             State::Unlocked => Ok(RawOnceState::Vacant),
             State::Write => Ok(RawOnceState::Vacant),
             State::Read => Ok(RawOnceState::Occupied),
-            State::Poison => Err(PoisonError::new(())),
+            State::Poison => Err(Error::Poisoned),
         }
     }
     unsafe fn unlock(&self) {
         assert_eq!(self.state.get(), State::Write);
         self.state.set(State::Unlocked);
-        self.writers.notify();
+        self.writers.notify_one();
+        self.readers.notify_all();
     }
     unsafe fn unlock_poison(&self) {
         assert_eq!(self.state.get(), State::Write);
         self.state.set(State::Poison);
-        self.writers.notify();
+        self.writers.notify_all();
+        self.readers.notify_all();
     }
 
     unsafe fn unlock_fuse(&self) {
         assert_eq!(self.state.get(), State::Write);
         self.state.set(State::Read);
-        self.writers.notify();
+        self.writers.notify_all();
+        self.readers.notify_all();
+    }
+    fn clear_poison(&self) -> bool {
+        if self.state.get() == State::Poison {
+            self.state.set(State::Unlocked);
+            true
+        } else {
+            false
+        }
     }
-    type WriteChecked<'a> = impl 'a + Future<Output = Result<RawOnceState, TryLockError<()>>>;
 
+    fn waiters(&self) -> usize {
+        self.waiters.get()
+    }
+
+    #[cfg(feature = "nightly")]
+    type WriteChecked<'a> = impl 'a + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
     fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
         self.write_checked_impl()
     }
+    #[cfg(not(feature = "nightly"))]
+    type WriteChecked<'a> = LocalBoxFuture<'a, Result<RawOnceState, Error>>;
+    #[cfg(not(feature = "nightly"))]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        Box::pin(self.write_checked_impl())
+    }
 
-    // type ReadChecked<'a> = impl 'a + Future<Output=Result<RawOnceState, TryLockError<()>>>;
-    //
-    // fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
-    //     async move {
-    //         todo!()
-    //     }
-    // }
+    #[cfg(feature = "nightly")]
+    type ReadChecked<'a> = impl 'a + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        self.read_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type ReadChecked<'a> = LocalBoxFuture<'a, Result<RawOnceState, Error>>;
+    #[cfg(not(feature = "nightly"))]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        Box::pin(self.read_checked_impl())
+    }
 }
@@ -1,10 +1,9 @@
-use futures::poll;
-use std::cell::{Cell, RefCell};
-use std::fmt::{Debug, Formatter};
-use std::future::{poll_fn, Future};
-use std::pin::Pin;
-use std::ptr::null;
-use std::task::{Context, Poll, Waker};
+use core::cell::Cell;
+use core::fmt::{Debug, Formatter};
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr::null;
+use core::task::{Context, Poll, Waker};
 
 #[derive(Copy, Clone, Debug)]
 enum WaiterState {
@@ -20,12 +19,15 @@ struct Waiter {
     state: Cell<WaiterState>,
 }
 
+/// A single-threaded, intrusive FIFO queue of tasks waiting for a notification.
 #[derive(Debug)]
 pub struct Condvar {
     front: Cell<*const Waiter>,
     back: Cell<*const Waiter>,
 }
 
+/// A received notification. Dropping it without calling [`Guard::consume`] passes the
+/// notification on to the next waiter.
 #[must_use]
 pub struct Guard<'a> {
     condvar: Option<&'a Condvar>,
@@ -49,9 +51,9 @@ impl<'a, 'w> Future for Wait<'a, 'w> {
             }
             WaiterState::Notified => {
                 this.waiter.state.set(WaiterState::Finished);
-                return Poll::Ready(Guard {
+                Poll::Ready(Guard {
                     condvar: Some(self.condvar),
-                });
+                })
             }
             WaiterState::Finished => {
                 panic!("Already finished")
@@ -65,7 +67,7 @@ impl<'a, 'w> Drop for Wait<'a, 'w> {
         unsafe {
             match self.waiter.state.get() {
                 WaiterState::Running => self.condvar.remove(self.waiter),
-                WaiterState::Notified => self.condvar.notify(),
+                WaiterState::Notified => self.condvar.notify_one(),
                 WaiterState::Finished => {}
             }
         }
@@ -81,21 +83,40 @@ impl Condvar {
     }
     unsafe fn push(&self, waiter: &Waiter) {
         let old_back = self.back.replace(waiter);
-        if old_back != null() {
+        waiter.prev.set(old_back);
+        waiter.next.set(null());
+        if old_back.is_null() {
+            self.front.set(waiter);
+        } else {
             (*old_back).next.set(waiter);
-            (*waiter).prev.set(old_back);
         }
     }
     unsafe fn remove(&self, waiter: &Waiter) {
-        if waiter.prev.get() == null() {
-            assert_eq!(self.front.get(), waiter);
-            self.front.set(waiter.next.get());
-            (*waiter.next.get()).prev.set(null());
+        let prev = waiter.prev.replace(null());
+        let next = waiter.next.replace(null());
+        if prev.is_null() {
+            assert_eq!(self.front.get(), waiter as *const Waiter);
+            self.front.set(next);
+        } else {
+            (*prev).next.set(next);
+        }
+        if next.is_null() {
+            assert_eq!(self.back.get(), waiter as *const Waiter);
+            self.back.set(prev);
         } else {
-            (*waiter.prev.get()).next.set(waiter.next.get())
+            (*next).prev.set(prev);
         }
     }
-    pub async fn wait(&self) -> Guard {
+    unsafe fn pop_front(&self) -> Option<&Waiter> {
+        let front = self.front.get();
+        if front.is_null() {
+            return None;
+        }
+        let front = &*front;
+        self.remove(front);
+        Some(front)
+    }
+    pub async fn wait(&self) -> Guard<'_> {
         unsafe {
             let waiter = Waiter {
                 next: Cell::new(null()),
@@ -105,13 +126,29 @@ impl Condvar {
             };
             self.push(&waiter);
             Wait {
-                condvar: &self,
+                condvar: self,
                 waiter: &waiter,
             }
             .await
         }
     }
-    pub fn notify(&self) {}
+    /// Wakes the longest-waiting task, if any.
+    pub fn notify_one(&self) {
+        unsafe {
+            if let Some(waiter) = self.pop_front() {
+                waiter.state.set(WaiterState::Notified);
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+    /// Wakes every task that is currently waiting.
+    pub fn notify_all(&self) {
+        while !self.front.get().is_null() {
+            self.notify_one();
+        }
+    }
 }
 
 impl<'a> Guard<'a> {
@@ -123,13 +160,13 @@ impl<'a> Guard<'a> {
 impl<'a> Drop for Guard<'a> {
     fn drop(&mut self) {
         if let Some(condvar) = self.condvar.take() {
-            condvar.notify();
+            condvar.notify_one();
         }
     }
 }
 
 impl Debug for Waiter {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Waiter")
             .field("next", &self.next)
             .field("prev", &self.prev)
@@ -1,12 +1,31 @@
 mod async_fused_cell;
 mod condvar;
 
-use crate::detached::DetachedFuture;
+#[cfg(test)]
+mod test;
+
+use crate::detached::{DetachedFuture, LocalBoxDetached};
 pub use async_fused_cell::AsyncRawFusedCell;
+use core::future::Future;
+use core::pin::Pin;
 use futures::future::LocalBoxFuture;
-use std::future::Future;
-use std::pin::Pin;
 
 pub type AsyncOnceCell<F> = crate::async_once::AsyncOnce<async_fused_cell::AsyncRawFusedCell, F>;
 
-pub type AsyncLazyCell<F> = crate::async_lazy::AsyncLazy<async_fused_cell::AsyncRawFusedCell, F>;
+/// An [`AsyncOnceCell`] named by its output type alone.
+pub type AsyncOnceBoxCell<T> =
+    crate::async_once_box::AsyncOnceBox<async_fused_cell::AsyncRawFusedCell, LocalBoxDetached<T>>;
+
+pub type AsyncLazyCell<F, I = fn() -> F> =
+    crate::async_lazy::AsyncLazy<async_fused_cell::AsyncRawFusedCell, F, I>;
+
+/// An [`AsyncLazyCell`] named by its output type alone, built with
+/// [`new_boxed`](crate::async_lazy::AsyncLazy::new_boxed).
+pub type AsyncLazyBoxCell<T> = AsyncLazyCell<LocalBoxDetached<T>>;
+
+#[cfg(feature = "std")]
+pub type AsyncRefreshCell<F> =
+    crate::async_refresh::AsyncRefresh<async_fused_cell::AsyncRawFusedCell, F>;
+
+pub type AsyncTryLazyCell<F, I = fn() -> F> =
+    crate::async_try_lazy::AsyncTryLazy<async_fused_cell::AsyncRawFusedCell, F, I>;
@@ -0,0 +1,350 @@
+use crate::async_fused::{AsyncFused, AsyncFusedEntry};
+use crate::cell::AsyncRawFusedCell;
+use crate::cell::{AsyncLazyBoxCell, AsyncOnceBoxCell, AsyncOnceCell};
+#[cfg(feature = "std")]
+use crate::detached::FuturesLocalSpawner;
+use crate::error::Error;
+use crate::raw::WriteScope;
+use crate::test_util::check_raw_lock;
+use futures::executor::LocalPool;
+use futures::future::{LocalBoxFuture, Map};
+use futures::FutureExt;
+use std::cell::{Cell, RefCell};
+use std::convert::identity;
+use std::rc::Rc;
+use tokio::task::{spawn_local, yield_now, LocalSet};
+
+async fn settle() {
+    for _ in 0..10 {
+        yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn test_fifo() {
+    LocalSet::new()
+        .run_until(async {
+            let fused = Rc::new(AsyncFused::<AsyncRawFusedCell, _>::new(vec![]));
+            let AsyncFusedEntry::Write(guard) = fused.write().await else {
+                unreachable!()
+            };
+            let tasks: Vec<_> = (0..5)
+                .map(|i| {
+                    let fused = fused.clone();
+                    spawn_local(async move {
+                        match fused.write().await {
+                            AsyncFusedEntry::Write(mut x) => x.push(i),
+                            AsyncFusedEntry::Read(_) => unreachable!(),
+                        }
+                    })
+                })
+                .collect();
+            settle().await;
+            tasks[2].abort();
+            settle().await;
+            drop(guard);
+            for task in tasks {
+                task.await.ok();
+            }
+            match fused.write().await {
+                AsyncFusedEntry::Write(x) => assert_eq!(*x, vec![0, 1, 3, 4]),
+                AsyncFusedEntry::Read(_) => unreachable!(),
+            };
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn test_fuse_wakes_all() {
+    LocalSet::new()
+        .run_until(async {
+            let fused = Rc::new(AsyncFused::<AsyncRawFusedCell, _>::new(0usize));
+            let AsyncFusedEntry::Write(mut guard) = fused.write().await else {
+                unreachable!()
+            };
+            let tasks: Vec<_> = (0..10)
+                .map(|_| {
+                    let fused = fused.clone();
+                    spawn_local(async move {
+                        match fused.write().await {
+                            AsyncFusedEntry::Write(_) => unreachable!(),
+                            AsyncFusedEntry::Read(x) => *x,
+                        }
+                    })
+                })
+                .collect();
+            settle().await;
+            *guard = 7;
+            guard.fuse();
+            for task in tasks {
+                assert_eq!(task.await.unwrap(), 7);
+            }
+        })
+        .await;
+}
+
+// Needs `std` to observe the panic.
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_poison_wakes_all() {
+    LocalSet::new()
+        .run_until(async {
+            let fused = Rc::new(AsyncFused::<AsyncRawFusedCell, _>::new(0usize));
+            let holder = spawn_local({
+                let fused = fused.clone();
+                async move {
+                    let guard = fused.write().await;
+                    settle().await;
+                    panic!("poison");
+                }
+            });
+            settle().await;
+            let tasks: Vec<_> = (0..10)
+                .map(|_| {
+                    let fused = fused.clone();
+                    spawn_local(async move { fused.write_checked().await.is_err() })
+                })
+                .collect();
+            assert!(holder.await.is_err());
+            for task in tasks {
+                assert!(task.await.unwrap());
+            }
+        })
+        .await;
+}
+
+#[test]
+fn test_try_write_errors() {
+    let fused = AsyncFused::<AsyncRawFusedCell, _>::new(0usize);
+    let guard = fused.try_write_checked();
+    assert!(matches!(guard, Ok(AsyncFusedEntry::Write(_))));
+    assert_eq!(fused.try_write_checked().err(), Some(Error::WouldBlock));
+    assert_eq!(fused.try_read_checked(), Ok(None));
+    drop(guard);
+    let poisoned = AsyncFused::<AsyncRawFusedCell, _>::poisoned(0usize);
+    assert_eq!(poisoned.try_write_checked().err(), Some(Error::Poisoned));
+    assert_eq!(poisoned.try_read_checked(), Err(Error::Poisoned));
+    assert_eq!(
+        Error::Poisoned.to_string(),
+        "poisoned by a panicking initializer"
+    );
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_once_contention() {
+    use crate::cell::AsyncOnceCell;
+    use crate::detached::{spawn_local_transparent, JoinTransparent};
+    LocalSet::new()
+        .run_until(async {
+            let once = Rc::new(AsyncOnceCell::<JoinTransparent<usize>>::new());
+            let inits = Rc::new(Cell::new(0));
+            let tasks: Vec<_> = (0..100)
+                .map(|_| {
+                    let once = once.clone();
+                    let inits = inits.clone();
+                    spawn_local(async move {
+                        *once
+                            .get_or_init_fn(|| {
+                                spawn_local_transparent(async move {
+                                    inits.set(inits.get() + 1);
+                                    settle().await;
+                                    42
+                                })
+                            })
+                            .await
+                    })
+                })
+                .collect();
+            for task in tasks {
+                assert_eq!(task.await.unwrap(), 42);
+            }
+            assert_eq!(inits.get(), 1);
+        })
+        .await;
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_once_cancelled_waiters() {
+    use crate::cell::AsyncOnceCell;
+    use crate::detached::{spawn_local_transparent, JoinTransparent};
+    LocalSet::new()
+        .run_until(async {
+            let once = Rc::new(AsyncOnceCell::<JoinTransparent<usize>>::new());
+            let inits = Rc::new(Cell::new(0));
+            let log = Rc::new(RefCell::new(vec![]));
+            let tasks: Vec<_> = (0..20)
+                .map(|i| {
+                    let once = once.clone();
+                    let inits = inits.clone();
+                    let log = log.clone();
+                    spawn_local(async move {
+                        let x = *once
+                            .get_or_init_fn(|| {
+                                spawn_local_transparent(async move {
+                                    inits.set(inits.get() + 1);
+                                    settle().await;
+                                    settle().await;
+                                    42
+                                })
+                            })
+                            .await;
+                        log.borrow_mut().push(i);
+                        x
+                    })
+                })
+                .collect();
+            settle().await;
+            for task in tasks.iter().step_by(2) {
+                task.abort();
+            }
+            for (i, task) in tasks.into_iter().enumerate() {
+                match task.await {
+                    Ok(x) => assert_eq!(x, 42),
+                    Err(e) => assert!(e.is_cancelled() && i % 2 == 0),
+                }
+            }
+            assert_eq!(inits.get(), 1);
+            assert_eq!(*log.borrow(), (1..20).step_by(2).collect::<Vec<_>>());
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn test_read_does_not_steal_wakeup() {
+    LocalSet::new()
+        .run_until(async {
+            let fused = Rc::new(AsyncFused::<AsyncRawFusedCell, _>::new(0usize));
+            let AsyncFusedEntry::Write(guard) = fused.write().await else {
+                unreachable!()
+            };
+            let reader = spawn_local({
+                let fused = fused.clone();
+                async move { fused.read().await.copied() }
+            });
+            let writer = spawn_local({
+                let fused = fused.clone();
+                async move {
+                    match fused.write().await {
+                        AsyncFusedEntry::Write(mut x) => {
+                            *x = 3;
+                            x.fuse();
+                        }
+                        AsyncFusedEntry::Read(_) => unreachable!(),
+                    }
+                }
+            });
+            settle().await;
+            drop(guard);
+            writer.await.unwrap();
+            assert_eq!(reader.await.unwrap(), Some(3));
+            assert_eq!(fused.read().await, Some(&3));
+        })
+        .await;
+}
+
+// Needs `std` to track write scopes.
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_recurrent() {
+    let fused = AsyncFused::<AsyncRawFusedCell, _>::new(1usize);
+    let AsyncFusedEntry::Write(guard) = fused.write().await else {
+        unreachable!()
+    };
+    WriteScope::new(guard.scope_id(), async {
+        assert!(matches!(fused.write_checked().await, Err(Error::Cycle)));
+        assert_eq!(fused.read_checked().await, Err(Error::Cycle));
+    })
+    .await;
+    drop(guard);
+    assert!(matches!(
+        fused.write_checked().await,
+        Ok(AsyncFusedEntry::Write(_))
+    ));
+}
+
+// Needs `std` to track write scopes.
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_recurrent_once() {
+    type Init = Map<LocalBoxFuture<'static, usize>, fn(usize) -> usize>;
+    let once: &'static AsyncOnceCell<Init> = Box::leak(Box::new(AsyncOnceCell::new()));
+    let init = async {
+        assert!(matches!(once.lock_checked().await, Err(Error::Cycle)));
+        2
+    };
+    let x = once
+        .get_or_init(init.boxed_local().map(identity as fn(usize) -> usize))
+        .await;
+    assert_eq!(*x, 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_spawner_local_pool() {
+    let mut pool = LocalPool::new();
+    let spawner = FuturesLocalSpawner(pool.spawner());
+    let once = AsyncOnceCell::new();
+    let value = pool.run_until(once.get_or_spawn(&spawner, || async { Rc::new(8) }));
+    assert_eq!(**value, 8);
+}
+
+#[tokio::test]
+async fn test_cancel_when_unused() {
+    type Init = Map<LocalBoxFuture<'static, usize>, fn(usize) -> usize>;
+    fn init(x: usize) -> Init {
+        async move {
+            settle().await;
+            x
+        }
+        .boxed_local()
+        .map(identity as fn(usize) -> usize)
+    }
+    LocalSet::new()
+        .run_until(async {
+            let once = Rc::new(AsyncOnceCell::<Init>::new_cancel_when_unused());
+            assert_eq!(once.get_or_init(init(1)).now_or_never(), None);
+            let holder = spawn_local({
+                let once = once.clone();
+                async move { *once.get_or_init(init(2)).await }
+            });
+            yield_now().await;
+            let waiter = spawn_local({
+                let once = once.clone();
+                async move { *once.get_or_init(init(3)).await }
+            });
+            yield_now().await;
+            holder.abort();
+            assert_eq!(waiter.await.unwrap(), 2);
+        })
+        .await;
+}
+
+#[test]
+fn test_raw_cell() {
+    check_raw_lock::<AsyncRawFusedCell>();
+}
+
+#[tokio::test]
+async fn test_waiters() {
+    let fused = AsyncFused::<AsyncRawFusedCell, _>::new(0usize);
+    let AsyncFusedEntry::Write(guard) = fused.write().await else {
+        unreachable!()
+    };
+    assert_eq!(guard.waiters(), 0);
+    let mut waiter = Box::pin(fused.write());
+    assert!((&mut waiter).now_or_never().is_none());
+    assert_eq!(guard.waiters(), 1);
+    drop(waiter);
+    assert_eq!(guard.waiters(), 0);
+}
+
+#[tokio::test]
+async fn test_boxed() {
+    let shared = Rc::new(5usize);
+    let once = AsyncOnceBoxCell::<Rc<usize>>::new();
+    assert_eq!(**once.get_or_init(async move { shared }).await, 5);
+    let lazy = AsyncLazyBoxCell::<usize>::new_boxed(async { 6 });
+    assert_eq!(*lazy.get().await, 6);
+}
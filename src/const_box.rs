@@ -1,91 +1,75 @@
-use std::fmt::{Debug, Formatter};
-use std::future::Future;
-use std::intrinsics::const_allocate;
-use std::marker::Unsize;
-use std::mem::{align_of, size_of};
-use std::ops::{CoerceUnsized, Deref, DerefMut};
-use std::pin::Pin;
-use std::process::abort;
-use std::ptr::{null, null_mut, NonNull};
-use std::slice::SliceIndex;
-use std::task::{Context, Poll};
+use alloc::boxed::Box;
+use core::fmt::{Debug, Formatter};
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
-/// It is a compilation error to initialize a ConstBox dynamically:
-/// ```compile_fail
-/// use safe_once_async::const_box::ConstBox;
-/// ConstBox::new(1);
-/// ```
-pub struct ConstBox<T: ?Sized>(NonNull<T>);
+type BoxFuture<T> = Pin<Box<dyn Send + Future<Output = T>>>;
 
-unsafe impl<T: ?Sized + Sync> Sync for ConstBox<T> {}
+/// A boxed future that can be constructed in a `const` context.
+///
+/// Heap allocations made during const evaluation cannot end up mutable in the final value of a
+/// `static`, so the future is described by a function pointer and only boxed the first time it is
+/// polled.
+pub struct ConstBoxFuture<T> {
+    state: ConstBoxState<T>,
+}
 
-unsafe impl<T: ?Sized + Send> Send for ConstBox<T> {}
+enum ConstBoxState<T> {
+    Init {
+        init: fn(),
+        boxed: unsafe fn(fn()) -> BoxFuture<T>,
+    },
+    Boxed(BoxFuture<T>),
+}
 
-#[allow(unconditional_recursion)]
-const fn cannot_dynamically_initialize_const_box<T>(x: T) {
-    return cannot_dynamically_initialize_const_box::<Option<T>>(Some(x));
+unsafe fn call_boxed<Fu: 'static + Send + Future>(init: fn()) -> BoxFuture<Fu::Output> {
+    let init = mem::transmute::<fn(), fn() -> Fu>(init);
+    Box::pin(init())
 }
 
-impl<T: ?Sized> ConstBox<T> {
-    pub const fn new(x: T) -> Self
+impl<T> ConstBoxFuture<T> {
+    pub const fn new<Fu>(init: fn() -> Fu) -> Self
     where
-        T: Sized,
+        Fu: 'static + Send + Future<Output = T>,
     {
-        unsafe {
-            let ptr = const_allocate(size_of::<T>(), align_of::<T>()) as *mut T;
-            if ptr.is_null() {
-                cannot_dynamically_initialize_const_box(1);
-            }
-            ptr.write(x);
-            ConstBox(NonNull::new(ptr).unwrap())
+        ConstBoxFuture {
+            state: ConstBoxState::Init {
+                init: unsafe { mem::transmute::<fn() -> Fu, fn()>(init) },
+                boxed: call_boxed::<Fu>,
+            },
         }
     }
-    pub const fn pin(x: T) -> Pin<Self>
-    where
-        T: Sized,
-    {
-        unsafe { Pin::new_unchecked(Self::new(x)) }
-    }
 }
 
-impl<T: ?Sized> Deref for ConstBox<T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.0.as_ref() }
-    }
-}
-
-impl<T: ?Sized> DerefMut for ConstBox<T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.0.as_mut() }
-    }
-}
-
-impl<T: ?Sized> Unpin for ConstBox<T> {}
-
-impl<T: Unsize<U> + ?Sized, U: ?Sized> CoerceUnsized<ConstBox<U>> for ConstBox<T> {}
-
-pub type ConstBoxFuture<T> = Pin<ConstBox<dyn Send + Future<Output = T>>>;
-
-impl<T: ?Sized + Future> Future for ConstBox<T> {
-    type Output = T::Output;
+impl<T> Future for ConstBoxFuture<T> {
+    type Output = T;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        todo!()
+        let this = self.get_mut();
+        if let ConstBoxState::Init { init, boxed } = this.state {
+            this.state = ConstBoxState::Boxed(unsafe { boxed(init) });
+        }
+        match &mut this.state {
+            ConstBoxState::Init { .. } => unreachable!(),
+            ConstBoxState::Boxed(fu) => fu.as_mut().poll(cx),
+        }
     }
 }
 
-impl<T: ?Sized + Debug> Debug for ConstBox<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        Debug::fmt(&**self, f)
+impl<T> Debug for ConstBoxFuture<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.state {
+            ConstBoxState::Init { .. } => write!(f, "ConstBoxFuture(init)"),
+            ConstBoxState::Boxed(_) => write!(f, "ConstBoxFuture(boxed)"),
+        }
     }
 }
 
 #[test]
 fn test_const_box() {
-    static X: ConstBox<usize> = ConstBox::new(10);
-    println!("{:?}", X);
+    static X: std::sync::Mutex<ConstBoxFuture<usize>> =
+        std::sync::Mutex::new(ConstBoxFuture::new(|| async { 10 }));
+    let mut x = X.lock().unwrap();
+    assert_eq!(futures::executor::block_on(&mut *x), 10);
 }
-
-type Foo = impl Future;
-
-static FOO: Foo = async move{};
\ No newline at end of file
@@ -0,0 +1,291 @@
+use crate::error::Error;
+#[cfg(not(feature = "nightly"))]
+use crate::raw::RawFuture;
+use crate::raw::{in_write_scope, AsyncRawFused, GuardSend, RawOnceState};
+#[cfg(not(feature = "nightly"))]
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt::{Debug, Formatter};
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr::null;
+use core::task::{Context, Poll, Waker};
+use critical_section::{CriticalSection, Mutex};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State {
+    Unlocked,
+    Write,
+    Read,
+    Poison,
+}
+
+/// An [`AsyncRawFused`] for targets without compare-and-swap, e.g. single-core
+/// microcontrollers.
+///
+/// Every operation runs inside a [critical section](critical_section::with), which the target
+/// provides by disabling interrupts, so the lock may be shared with interrupt handlers. Tasks
+/// waiting for the write lock are linked into a list that lives inside their futures. Releasing
+/// the lock wakes all of them.
+pub struct AsyncRawFusedCriticalLock {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    state: Cell<State>,
+    head: Cell<*const Waiter>,
+}
+
+// The waiter list is only reached through the mutex, i.e. inside a critical section.
+unsafe impl Send for Inner {}
+
+struct Waiter {
+    next: Cell<*const Waiter>,
+    prev: Cell<*const Waiter>,
+    waker: Cell<Option<Waker>>,
+    notified: Cell<bool>,
+    /// Whether the waiter is in `write_checked`. Only those are counted by `waiters`.
+    writer: bool,
+}
+
+// Waiters are only touched inside a critical section, by their own future or by `release`.
+unsafe impl Send for Waiter {}
+unsafe impl Sync for Waiter {}
+
+/// Waits to be unlinked from the list and woken. If dropped while still linked, the waiter
+/// unlinks itself.
+struct Wait<'a, 'w> {
+    lock: &'a AsyncRawFusedCriticalLock,
+    waiter: &'w Waiter,
+}
+
+impl<'a, 'w> Unpin for Wait<'a, 'w> {}
+
+impl<'a, 'w> Future for Wait<'a, 'w> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        critical_section::with(|_| {
+            if self.waiter.notified.get() {
+                return Poll::Ready(());
+            }
+            self.waiter.waker.set(Some(cx.waker().clone()));
+            Poll::Pending
+        })
+    }
+}
+
+impl<'a, 'w> Drop for Wait<'a, 'w> {
+    fn drop(&mut self) {
+        critical_section::with(|cs| {
+            if !self.waiter.notified.get() {
+                self.lock.remove(cs, self.waiter);
+            }
+        })
+    }
+}
+
+impl AsyncRawFusedCriticalLock {
+    const fn with_state(state: State) -> Self {
+        AsyncRawFusedCriticalLock {
+            inner: Mutex::new(Inner {
+                state: Cell::new(state),
+                head: Cell::new(null()),
+            }),
+        }
+    }
+    fn state(&self, cs: CriticalSection<'_>) -> State {
+        self.inner.borrow(cs).state.get()
+    }
+    fn push(&self, cs: CriticalSection<'_>, waiter: &Waiter) {
+        let inner = self.inner.borrow(cs);
+        let head = inner.head.replace(waiter);
+        waiter.next.set(head);
+        if !head.is_null() {
+            unsafe { (*head).prev.set(waiter) };
+        }
+    }
+    fn remove(&self, cs: CriticalSection<'_>, waiter: &Waiter) {
+        let prev = waiter.prev.replace(null());
+        let next = waiter.next.replace(null());
+        if prev.is_null() {
+            self.inner.borrow(cs).head.set(next);
+        } else {
+            unsafe { (*prev).next.set(next) };
+        }
+        if !next.is_null() {
+            unsafe { (*next).prev.set(prev) };
+        }
+    }
+    /// Leaves the write lock in `new_state` and wakes every waiter.
+    fn release(&self, new_state: State) {
+        // Wakers may run arbitrary code, so they are only called once interrupts are enabled again.
+        let wakers: Vec<Waker> = critical_section::with(|cs| {
+            let inner = self.inner.borrow(cs);
+            assert_eq!(inner.state.replace(new_state), State::Write);
+            let mut wakers = Vec::new();
+            let mut waiter = inner.head.replace(null());
+            while !waiter.is_null() {
+                unsafe {
+                    let next = (*waiter).next.get();
+                    wakers.extend((*waiter).waker.take());
+                    // The waiter may be freed as soon as the critical section ends.
+                    (*waiter).notified.set(true);
+                    waiter = next;
+                }
+            }
+            wakers
+        });
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+    /// Waits until the write lock is released, or returns immediately if it is not held.
+    async fn wait(&self, writer: bool) {
+        let waiter = Waiter {
+            next: Cell::new(null()),
+            prev: Cell::new(null()),
+            waker: Cell::new(None),
+            notified: Cell::new(false),
+            writer,
+        };
+        let pushed = critical_section::with(|cs| {
+            if self.state(cs) != State::Write {
+                return false;
+            }
+            self.push(cs, &waiter);
+            true
+        });
+        if !pushed {
+            return;
+        }
+        Wait {
+            lock: self,
+            waiter: &waiter,
+        }
+        .await
+    }
+    async fn write_checked_impl(&self) -> Result<RawOnceState, Error> {
+        loop {
+            match self.try_write_checked() {
+                Err(Error::WouldBlock) => {}
+                result => return result,
+            }
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            self.wait(true).await;
+        }
+    }
+    async fn read_checked_impl(&self) -> Result<RawOnceState, Error> {
+        while critical_section::with(|cs| self.state(cs)) == State::Write {
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            self.wait(false).await;
+        }
+        self.try_read_checked()
+    }
+}
+
+unsafe impl AsyncRawFused for AsyncRawFusedCriticalLock {
+    type GuardMarker = GuardSend;
+    const UNLOCKED: Self = Self::with_state(State::Unlocked);
+    const READ: Self = Self::with_state(State::Read);
+    const POISON: Self = Self::with_state(State::Poison);
+
+    fn try_write_checked(&self) -> Result<RawOnceState, Error> {
+        critical_section::with(|cs| {
+            let state = &self.inner.borrow(cs).state;
+            match state.get() {
+                State::Unlocked => {
+                    state.set(State::Write);
+                    Ok(RawOnceState::Vacant)
+                }
+                State::Write => Err(Error::WouldBlock),
+                State::Read => Ok(RawOnceState::Occupied),
+                State::Poison => Err(Error::Poisoned),
+            }
+        })
+    }
+
+    fn try_read_checked(&self) -> Result<RawOnceState, Error> {
+        match critical_section::with(|cs| self.state(cs)) {
+            State::Unlocked | State::Write => Ok(RawOnceState::Vacant),
+            State::Read => Ok(RawOnceState::Occupied),
+            State::Poison => Err(Error::Poisoned),
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.release(State::Unlocked);
+    }
+
+    unsafe fn unlock_poison(&self) {
+        self.release(State::Poison);
+    }
+
+    unsafe fn unlock_fuse(&self) {
+        self.release(State::Read);
+    }
+
+    fn clear_poison(&self) -> bool {
+        critical_section::with(|cs| {
+            let state = &self.inner.borrow(cs).state;
+            if state.get() != State::Poison {
+                return false;
+            }
+            state.set(State::Unlocked);
+            true
+        })
+    }
+
+    fn waiters(&self) -> usize {
+        critical_section::with(|cs| {
+            let mut count = 0;
+            let mut waiter = self.inner.borrow(cs).head.get();
+            while !waiter.is_null() {
+                let waiter_ref = unsafe { &*waiter };
+                count += usize::from(waiter_ref.writer);
+                waiter = waiter_ref.next.get();
+            }
+            count
+        })
+    }
+
+    #[cfg(feature = "nightly")]
+    type WriteChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        self.write_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type WriteChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        Box::pin(self.write_checked_impl())
+    }
+
+    #[cfg(feature = "nightly")]
+    type ReadChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        self.read_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type ReadChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        Box::pin(self.read_checked_impl())
+    }
+}
+
+impl Debug for AsyncRawFusedCriticalLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        critical_section::with(|cs| {
+            f.debug_struct("AsyncRawFusedCriticalLock")
+                .field("state", &self.state(cs))
+                .finish()
+        })
+    }
+}
@@ -0,0 +1,20 @@
+mod async_fused_critical_lock;
+
+#[cfg(test)]
+mod test;
+
+use crate::detached::BoxDetached;
+pub use async_fused_critical_lock::AsyncRawFusedCriticalLock;
+
+pub type AsyncOnceCritical<F> = crate::async_once::AsyncOnce<AsyncRawFusedCriticalLock, F>;
+/// An [`AsyncOnceCritical`] named by its output type alone.
+pub type AsyncOnceBoxCritical<T> =
+    crate::async_once_box::AsyncOnceBox<AsyncRawFusedCriticalLock, BoxDetached<T>>;
+pub type AsyncLazyCritical<F, I = fn() -> F> =
+    crate::async_lazy::AsyncLazy<AsyncRawFusedCriticalLock, F, I>;
+/// An [`AsyncLazyCritical`] named by its output type alone, built with
+/// [`new_boxed`](crate::async_lazy::AsyncLazy::new_boxed).
+pub type AsyncLazyBoxCritical<T> = AsyncLazyCritical<BoxDetached<T>>;
+pub type AsyncTryLazyCritical<F, I = fn() -> F> =
+    crate::async_try_lazy::AsyncTryLazy<AsyncRawFusedCriticalLock, F, I>;
+pub type AsyncStaticCritical<T> = crate::async_static::AsyncStatic<AsyncRawFusedCriticalLock, T>;
@@ -0,0 +1,37 @@
+use crate::critical::{
+    AsyncLazyCritical, AsyncOnceBoxCritical, AsyncRawFusedCriticalLock, AsyncStaticCritical,
+};
+use crate::detached::BoxDetached;
+use crate::test_util::{check_raw_lock, check_raw_lock_threads};
+use futures::executor::block_on;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+#[test]
+fn test_critical_lock() {
+    check_raw_lock::<AsyncRawFusedCriticalLock>();
+}
+
+#[test]
+fn test_critical_statics() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    static PERIPHERAL: AsyncLazyCritical<BoxDetached<usize>> = AsyncLazyCritical::new_with(|| {
+        CALLS.fetch_add(1, Relaxed);
+        BoxDetached::new(async { 12 })
+    });
+    static STATIC: AsyncStaticCritical<usize> = AsyncStaticCritical::new(|| async { 5 });
+    static ONCE: AsyncOnceBoxCritical<usize> = AsyncOnceBoxCritical::new();
+    block_on(async {
+        assert_eq!(PERIPHERAL.try_get(), None);
+        assert_eq!(*PERIPHERAL.get().await, 12);
+        assert_eq!(*PERIPHERAL.get().await, 12);
+        assert_eq!(CALLS.load(Relaxed), 1);
+        assert_eq!(*STATIC.get().await, 5);
+        assert_eq!(*ONCE.get_or_init_fn(|| async { 4 }).await, 4);
+    });
+}
+
+#[test]
+fn test_critical_lock_threads() {
+    check_raw_lock_threads::<AsyncRawFusedCriticalLock>();
+}
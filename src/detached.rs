@@ -72,28 +72,292 @@
 //     }
 // }
 
-use std::future::Future;
-use std::pin::Pin;
-use std::rc::Rc;
-use std::task::{Context, Poll};
+use crate::error::Error;
+use crate::mut_cell::MutCell;
+#[cfg(feature = "std")]
+use crate::raw::InheritScopes;
+use alloc::boxed::Box;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+#[cfg(feature = "std")]
+use futures::future::RemoteHandle;
+#[cfg(feature = "std")]
+use futures::task::{LocalSpawn, LocalSpawnExt, Spawn, SpawnExt};
+use pin_project::pin_project;
+#[cfg(feature = "tokio-rt")]
+use std::panic::{catch_unwind, panic_any, resume_unwind, AssertUnwindSafe};
+
+pub trait DetachedFuture: Future {
+    /// Lets the underlying work run to completion even if this handle is dropped. By default
+    /// handles cancel their work when dropped; implementations that cannot be kept alive
+    /// ignore this.
+    fn keep_alive(self: Pin<&mut Self>) {}
+}
+
+/// Starts futures on an executor, returning a handle that cancels the future when dropped.
+///
+/// The crate's spawners carry the caller's write scopes over to the spawned task, so that an
+/// initializer that waits for its own cell fails with [`Error::Cycle`] instead of deadlocking.
+/// Other implementations spawn the future as is.
+pub trait Spawner<Fu: Future> {
+    type Handle: Unpin + DetachedFuture<Output = Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle;
+}
+
+impl<S: Spawner<Fu>, Fu: Future> Spawner<Fu> for &S {
+    type Handle = S::Handle;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        (**self).spawn(future)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: 'static> DetachedFuture for RemoteHandle<T> {}
+
+/// A [`RemoteHandle`] that can be [kept alive](DetachedFuture::keep_alive).
+#[cfg(feature = "std")]
+pub struct RemoteJoin<T> {
+    inner: Option<RemoteHandle<T>>,
+    keep_alive: bool,
+}
+
+#[cfg(feature = "std")]
+impl<T> Unpin for RemoteJoin<T> {}
+
+#[cfg(feature = "std")]
+impl<T: 'static> Future for RemoteJoin<T> {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(self.inner.as_mut().unwrap()).poll(cx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: 'static> DetachedFuture for RemoteJoin<T> {
+    fn keep_alive(mut self: Pin<&mut Self>) {
+        self.keep_alive = true;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Drop for RemoteJoin<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            if self.keep_alive {
+                inner.forget();
+            }
+        }
+    }
+}
+
+/// Spawns onto a [`futures::task::Spawn`] implementation such as
+/// `futures::executor::ThreadPool`. Panics in the spawned future resume in the awaiting task.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct FuturesSpawner<S>(pub S);
+
+#[cfg(feature = "std")]
+impl<S: Spawn, Fu: 'static + Send + Future> Spawner<Fu> for FuturesSpawner<S>
+where
+    Fu::Output: Send,
+{
+    type Handle = RemoteJoin<Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        RemoteJoin {
+            inner: Some(
+                self.0
+                    .spawn_with_handle(InheritScopes::new(future))
+                    .expect("executor has shut down"),
+            ),
+            keep_alive: false,
+        }
+    }
+}
+
+/// Spawns onto a [`futures::task::LocalSpawn`] implementation such as
+/// `futures::executor::LocalSpawner`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct FuturesLocalSpawner<S>(pub S);
+
+#[cfg(feature = "std")]
+impl<S: LocalSpawn, Fu: 'static + Future> Spawner<Fu> for FuturesLocalSpawner<S> {
+    type Handle = RemoteJoin<Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        RemoteJoin {
+            inner: Some(
+                self.0
+                    .spawn_local_with_handle(InheritScopes::new(future))
+                    .expect("executor has shut down"),
+            ),
+            keep_alive: false,
+        }
+    }
+}
+
+/// A future that is handed to a [`Spawner`] the first time it is polled, so that constructing
+/// it (e.g. in [`AsyncLazy::new_spawned`](crate::async_lazy::AsyncLazy::new_spawned)) does not
+/// start any work.
+pub struct SpawnOnPoll<S: Spawner<Fu>, Fu: Future> {
+    state: SpawnOnPollState<S, Fu>,
+    keep_alive: bool,
+}
+
+enum SpawnOnPollState<S: Spawner<Fu>, Fu: Future> {
+    Pending(Option<(S, Fu)>),
+    Spawned(S::Handle),
+}
+
+impl<S: Spawner<Fu>, Fu: Future> SpawnOnPoll<S, Fu> {
+    pub fn new(spawner: S, future: Fu) -> Self {
+        SpawnOnPoll {
+            state: SpawnOnPollState::Pending(Some((spawner, future))),
+            keep_alive: false,
+        }
+    }
+}
+
+// The inner future is moved into the spawner before it is ever pinned.
+impl<S: Spawner<Fu>, Fu: Future> Unpin for SpawnOnPoll<S, Fu> {}
+
+impl<S: Spawner<Fu>, Fu: Future> Future for SpawnOnPoll<S, Fu> {
+    type Output = Fu::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let SpawnOnPollState::Pending(pending) = &mut this.state {
+            let (spawner, future) = pending.take().unwrap();
+            let mut handle = spawner.spawn(future);
+            if this.keep_alive {
+                Pin::new(&mut handle).keep_alive();
+            }
+            this.state = SpawnOnPollState::Spawned(handle);
+        }
+        match &mut this.state {
+            SpawnOnPollState::Pending(_) => unreachable!(),
+            SpawnOnPollState::Spawned(handle) => Pin::new(handle).poll(cx),
+        }
+    }
+}
+
+impl<S: Spawner<Fu>, Fu: Future> DetachedFuture for SpawnOnPoll<S, Fu> {
+    fn keep_alive(self: Pin<&mut Self>) {
+        let this = self.get_mut();
+        this.keep_alive = true;
+        if let SpawnOnPollState::Spawned(handle) = &mut this.state {
+            Pin::new(handle).keep_alive();
+        }
+    }
+}
+
+/// A boxed `Send` future of any type, so that cells can be named by their output alone.
+pub struct BoxDetached<T> {
+    inner: MutCell<Pin<Box<dyn Send + DetachedFuture<Output = T>>>>,
+}
+
+impl<T> BoxDetached<T> {
+    pub fn new(f: impl 'static + Send + Future<Output = T>) -> Self {
+        Self::new_detached(Inline(f))
+    }
+    /// Boxes a handle such as one returned by a [`Spawner`], forwarding
+    /// [`keep_alive`](DetachedFuture::keep_alive) to it.
+    pub fn new_detached(f: impl 'static + Send + DetachedFuture<Output = T>) -> Self {
+        BoxDetached {
+            inner: MutCell::new(Box::pin(f)),
+        }
+    }
+}
+
+impl<T> Future for BoxDetached<T> {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner).poll(cx)
+    }
+}
+
+impl<T> DetachedFuture for BoxDetached<T> {
+    fn keep_alive(mut self: Pin<&mut Self>) {
+        AsMut::as_mut(&mut self.inner).as_mut().keep_alive();
+    }
+}
+
+/// A boxed future of any type, for cells that are only used from one thread.
+pub struct LocalBoxDetached<T> {
+    inner: Pin<Box<dyn DetachedFuture<Output = T>>>,
+}
+
+impl<T> LocalBoxDetached<T> {
+    pub fn new(f: impl 'static + Future<Output = T>) -> Self {
+        Self::new_detached(Inline(f))
+    }
+    /// Boxes a handle such as one returned by a [`Spawner`], forwarding
+    /// [`keep_alive`](DetachedFuture::keep_alive) to it.
+    pub fn new_detached(f: impl 'static + DetachedFuture<Output = T>) -> Self {
+        LocalBoxDetached { inner: Box::pin(f) }
+    }
+}
 
-pub trait DetachedFuture: Future {}
+impl<T> Future for LocalBoxDetached<T> {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+impl<T> DetachedFuture for LocalBoxDetached<T> {
+    fn keep_alive(mut self: Pin<&mut Self>) {
+        self.inner.as_mut().keep_alive();
+    }
+}
+
+/// A plain future, which does its work inside the handle and so has nothing to keep alive.
+#[pin_project]
+struct Inline<Fu>(#[pin] Fu);
+
+impl<Fu: Future> Future for Inline<Fu> {
+    type Output = Fu::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+impl<Fu: Future> DetachedFuture for Inline<Fu> {}
 
-impl<F: ?Sized + DetachedFuture> DetachedFuture for Pin<Box<F>> {}
+impl<F: ?Sized + DetachedFuture> DetachedFuture for Pin<Box<F>> {
+    fn keep_alive(self: Pin<&mut Self>) {
+        self.get_mut().as_mut().keep_alive();
+    }
+}
 
 #[cfg(feature = "tokio-rt")]
 impl<T> DetachedFuture for tokio::task::JoinHandle<T> {}
 
+// `Map` does not expose the future it wraps, so `keep_alive` cannot be forwarded to it. Map the
+// future before spawning it instead, or let `AsyncOnce::get_or_spawn_detached` drive the handle
+// from its own task.
 impl<Fu: Future, F: FnOnce(Fu::Output) -> T, T> DetachedFuture for futures::future::Map<Fu, F> {}
 
 #[cfg(feature = "tokio-rt")]
 pub struct JoinTransparent<T> {
     inner: tokio::task::JoinHandle<T>,
+    keep_alive: bool,
 }
 
 #[cfg(feature = "tokio-rt")]
 impl<T> Unpin for JoinTransparent<T> {}
 
+#[cfg(feature = "tokio-rt")]
+impl<T> JoinTransparent<T> {
+    /// Cancels the spawned task. Awaiting the handle afterwards panics with
+    /// [`Error::Cancelled`] unless the task already finished.
+    pub fn abort(&self) {
+        self.inner.abort();
+    }
+}
+
+/// Re-raises the panic of the spawned task in the task that awaits it. If the spawned task was
+/// cancelled instead, for example because its runtime shut down, the awaiting task panics with
+/// an [`Error::Cancelled`] payload.
 #[cfg(feature = "tokio-rt")]
 impl<T> Future for JoinTransparent<T> {
     type Output = T;
@@ -101,17 +365,68 @@ impl<T> Future for JoinTransparent<T> {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         Pin::new(&mut self.as_mut().inner)
             .poll(cx)
-            .map(|x| x.unwrap())
+            .map(|x| match x {
+                Ok(x) => x,
+                Err(e) if e.is_panic() => resume_unwind(e.into_panic()),
+                Err(_) => panic_any(Error::Cancelled),
+            })
+    }
+}
+
+/// Like [`JoinTransparent`], but a panic in the spawned task is reported as
+/// [`Error::InitFailed`] and a cancellation as [`Error::Cancelled`]. Used with
+/// [`get_or_try_init`](crate::async_once::AsyncOnce::get_or_try_init), a failed task leaves the
+/// cell vacant for a retry instead of poisoning it.
+///
+/// The panic payload is dropped: the cell discards a failed handle, so nothing could read it.
+#[cfg(feature = "tokio-rt")]
+pub struct TryJoinTransparent<T> {
+    inner: JoinTransparent<T>,
+}
+
+#[cfg(feature = "tokio-rt")]
+impl<T> TryJoinTransparent<T> {
+    pub fn abort(&self) {
+        self.inner.abort();
     }
 }
 
 #[cfg(feature = "tokio-rt")]
-impl<T> DetachedFuture for JoinTransparent<T> {}
+impl<T> Future for TryJoinTransparent<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.inner;
+        match catch_unwind(AssertUnwindSafe(|| Pin::new(inner).poll(cx))) {
+            Ok(x) => x.map(Ok),
+            Err(payload) => Poll::Ready(Err(match payload.downcast_ref::<Error>() {
+                Some(Error::Cancelled) => Error::Cancelled,
+                _ => Error::InitFailed,
+            })),
+        }
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl<T> DetachedFuture for TryJoinTransparent<T> {
+    fn keep_alive(mut self: Pin<&mut Self>) {
+        Pin::new(&mut self.inner).keep_alive();
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl<T> DetachedFuture for JoinTransparent<T> {
+    fn keep_alive(mut self: Pin<&mut Self>) {
+        self.keep_alive = true;
+    }
+}
 
 #[cfg(feature = "tokio-rt")]
 impl<T> Drop for JoinTransparent<T> {
     fn drop(&mut self) {
-        self.inner.abort();
+        if !self.keep_alive {
+            self.inner.abort();
+        }
     }
 }
 
@@ -121,13 +436,68 @@ where
     Fu::Output: Send,
 {
     JoinTransparent {
-        inner: tokio::spawn(f),
+        inner: tokio::spawn(InheritScopes::new(f)),
+        keep_alive: false,
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+pub fn spawn_try_transparent<Fu: 'static + Send + Future>(f: Fu) -> TryJoinTransparent<Fu::Output>
+where
+    Fu::Output: Send,
+{
+    TryJoinTransparent {
+        inner: spawn_transparent(f),
     }
 }
 
 #[cfg(feature = "tokio-rt")]
 pub fn spawn_local_transparent<Fu: 'static + Future>(f: Fu) -> JoinTransparent<Fu::Output> {
     JoinTransparent {
-        inner: tokio::task::spawn_local(f),
+        inner: tokio::task::spawn_local(InheritScopes::new(f)),
+        keep_alive: false,
+    }
+}
+
+/// Spawns onto the current tokio runtime with [`tokio::spawn`].
+#[cfg(feature = "tokio-rt")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TokioSpawner;
+
+#[cfg(feature = "tokio-rt")]
+impl<Fu: 'static + Send + Future> Spawner<Fu> for TokioSpawner
+where
+    Fu::Output: Send,
+{
+    type Handle = JoinTransparent<Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        spawn_transparent(future)
+    }
+}
+
+/// Spawns onto the current [`tokio::task::LocalSet`] with [`tokio::task::spawn_local`].
+#[cfg(feature = "tokio-rt")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TokioLocalSpawner;
+
+#[cfg(feature = "tokio-rt")]
+impl<Fu: 'static + Future> Spawner<Fu> for TokioLocalSpawner {
+    type Handle = JoinTransparent<Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        spawn_local_transparent(future)
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl<Fu: 'static + Send + Future> Spawner<Fu> for tokio::runtime::Handle
+where
+    Fu::Output: Send,
+{
+    type Handle = JoinTransparent<Fu::Output>;
+    fn spawn(&self, future: Fu) -> Self::Handle {
+        JoinTransparent {
+            inner: tokio::runtime::Handle::spawn(self, InheritScopes::new(future)),
+            keep_alive: false,
+        }
     }
 }
@@ -0,0 +1,35 @@
+use core::fmt::{Display, Formatter};
+
+/// The ways in which acquiring or initializing an async once cell can fail.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// A previous initializer panicked while holding the write lock.
+    Poisoned,
+    /// The lock is held by another task and the caller asked not to wait.
+    WouldBlock,
+    /// The lock was requested from inside the future that is initializing it, so waiting for it
+    /// would deadlock.
+    Cycle,
+    /// The initializer was cancelled before producing a value.
+    Cancelled,
+    /// The initializer finished without producing a value.
+    InitFailed,
+    /// A timeout or deadline elapsed before the value was available.
+    TimedOut,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Poisoned => write!(f, "poisoned by a panicking initializer"),
+            Error::WouldBlock => write!(f, "lock is held by another task"),
+            Error::Cycle => write!(f, "initializer depends on itself"),
+            Error::Cancelled => write!(f, "initializer was cancelled"),
+            Error::InitFailed => write!(f, "initializer failed"),
+            Error::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
@@ -1,22 +1,16 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![deny(unused_must_use)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 #![allow(unused_assignments)]
-#![feature(impl_trait_in_assoc_type)]
+#![cfg_attr(feature = "nightly", feature(impl_trait_in_assoc_type))]
 #![allow(dead_code)]
-#![feature(type_alias_impl_trait)]
-#![feature(const_async_blocks)]
-#![feature(const_heap)]
-#![feature(unsize)]
-#![feature(coerce_unsized)]
-#![feature(exclusive_wrapper)]
 #![allow(unused_mut)]
-#![feature(trait_alias)]
-#![feature(core_intrinsics)]
-#![allow(internal_features)]
+#![cfg_attr(feature = "nightly", feature(trait_alias))]
 
 //!
 //! ```
+//! # #[cfg(feature = "std")] mod test {
 //! use std::future::Future;
 //! use std::pin::Pin;
 //! use safe_once_async::detached::DetachedFuture;
@@ -24,6 +18,7 @@
 //! fn init(x:&AsyncLazyLock<Pin<Box<dyn Send+DetachedFuture<Output=usize>>>>) -> impl Send+Future<Output=&usize>{
 //!     x.get()
 //! }
+//! # }
 //! ```
 //!
 //! ```compile_fail
@@ -48,28 +43,48 @@
 //! ```
 //!
 
+extern crate alloc;
+
 pub mod raw;
 
-use std::cell::UnsafeCell;
-use std::fmt::{Debug, Display, Formatter};
-use std::future::poll_fn;
-use std::future::Future;
-use std::marker::PhantomData;
-use std::mem;
-use std::mem::MaybeUninit;
-use std::panic::{RefUnwindSafe, UnwindSafe};
-use std::pin::{pin, Pin};
-use std::task::{Context, Poll};
-use std::thread::panicking;
+use core::cell::UnsafeCell;
+use core::fmt::{Debug, Display, Formatter};
+use core::future::poll_fn;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem;
+use core::mem::MaybeUninit;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll};
 
+#[cfg(feature = "std")]
 pub mod sync;
 
 pub mod cell;
 
+#[cfg(target_has_atomic = "ptr")]
+pub mod word;
+
+#[cfg(feature = "critical-section")]
+pub mod critical;
+
 pub mod async_fused;
 pub mod async_lazy;
 pub mod async_once;
-// pub mod async_static;
-// pub mod const_box;
+pub mod async_once_box;
+#[cfg(feature = "std")]
+pub mod async_refresh;
+pub mod async_static;
+pub mod async_try_lazy;
+#[cfg(feature = "std")]
+mod blocking;
+pub mod const_box;
 pub mod detached;
+pub mod error;
+mod mut_cell;
+#[cfg(feature = "nightly")]
+mod nightly;
+#[cfg(test)]
+mod test_util;
 mod thunk;
@@ -1,7 +1,9 @@
-use std::future::Future;
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
+/// Only hands out `&mut` access to its contents, and is therefore `Sync` whenever they are
+/// `Send`. A stable stand-in for `std::sync::Exclusive`.
 pub struct MutCell<T>(T);
 
 impl<T> AsMut<T> for MutCell<T> {
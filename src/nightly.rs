@@ -0,0 +1,8 @@
+use crate::raw::AsyncRawFused;
+
+/// A raw lock that can be shared between threads, with `Send` futures.
+pub trait AsyncRawFusedSync = AsyncRawFused + Sync + Send
+where
+    <Self as AsyncRawFused>::GuardMarker: Send,
+    for<'a> <Self as AsyncRawFused>::WriteChecked<'a>: Send,
+    for<'a> <Self as AsyncRawFused>::ReadChecked<'a>: Send;
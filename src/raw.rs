@@ -1,34 +1,259 @@
-use std::future::Future;
-use std::marker::PhantomData;
-use std::sync::PoisonError;
-use std::sync::TryLockError;
+use crate::error::Error;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+#[cfg(not(feature = "nightly"))]
+use futures::future::BoxFuture;
+use pin_project::pin_project;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+
+/// [`AsyncRawFused::GuardMarker`] for raw locks whose write guard may be sent to another thread.
+pub struct GuardSend(());
+
+/// [`AsyncRawFused::GuardMarker`] for raw locks whose write guard must stay on its thread.
+pub struct GuardNoSend(*mut ());
 
 pub enum RawOnceState {
     Occupied,
     Vacant,
 }
 
+#[cfg(feature = "std")]
+thread_local! {
+    static WRITE_SCOPES: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether the current thread is unwinding from a panic.
+#[cfg(feature = "std")]
+pub(crate) fn panicking() -> bool {
+    std::thread::panicking()
+}
+
+/// Without `std` an unwinding panic cannot be observed, so guards always take the non-panicking
+/// path.
+#[cfg(not(feature = "std"))]
+pub(crate) fn panicking() -> bool {
+    false
+}
+
+/// Identifies a raw lock whose write lock is held while a [`WriteScope`] is polled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ScopeId(usize);
+
+impl ScopeId {
+    pub(crate) fn new<R>(raw: &R) -> Self {
+        ScopeId(raw as *const R as usize)
+    }
+    /// Calls `f` inside this scope, so that an initializer it spawns through one of the crate's
+    /// spawners inherits the scope.
+    pub(crate) fn enter<O>(self, f: impl FnOnce() -> O) -> O {
+        let _exit = ExitScope::enter(&[self.0]);
+        f()
+    }
+}
+
+/// Returns true if the caller is being polled from inside a [`WriteScope`] for `raw`, i.e. by
+/// the holder of its write lock.
+#[cfg(feature = "std")]
+pub(crate) fn in_write_scope<R>(raw: &R) -> bool {
+    let id = ScopeId::new(raw);
+    WRITE_SCOPES.with(|scopes| scopes.borrow().contains(&id.0))
+}
+
+/// Write scopes are tracked in a thread local, which is not available without `std`. Cycles
+/// then wait forever instead of reporting [`Error::Cycle`].
+#[cfg(not(feature = "std"))]
+pub(crate) fn in_write_scope<R>(raw: &R) -> bool {
+    false
+}
+
+/// A future polled on behalf of the holder of a write lock. Attempts to take the same lock while
+/// it is being polled report [`Error::Cycle`] rather than deadlocking.
+#[pin_project]
+pub(crate) struct WriteScope<Fu> {
+    id: ScopeId,
+    #[pin]
+    inner: Fu,
+}
+
+impl<Fu> WriteScope<Fu> {
+    pub(crate) fn new(id: ScopeId, inner: Fu) -> Self {
+        WriteScope { id, inner }
+    }
+}
+
+/// Restores the write scopes of the thread to what they were before [`ExitScope::enter`].
+struct ExitScope {
+    #[cfg(feature = "std")]
+    len: usize,
+}
+
+impl ExitScope {
+    fn enter(ids: &[usize]) -> Self {
+        #[cfg(feature = "std")]
+        return WRITE_SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            let len = scopes.len();
+            scopes.extend_from_slice(ids);
+            ExitScope { len }
+        });
+        #[cfg(not(feature = "std"))]
+        ExitScope {}
+    }
+}
+
+impl Drop for ExitScope {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        WRITE_SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.len));
+    }
+}
+
+impl<Fu: Future> Future for WriteScope<Fu> {
+    type Output = Fu::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _exit = ExitScope::enter(&[this.id.0]);
+        this.inner.poll(cx)
+    }
+}
+
+/// A spawned future that is polled inside the write scopes that were entered when it was
+/// created. An initializer spawned onto another task then still reports [`Error::Cycle`] when it
+/// waits for its own cell, instead of waiting for itself forever.
+#[cfg(feature = "std")]
+#[pin_project]
+pub(crate) struct InheritScopes<Fu> {
+    scopes: Vec<usize>,
+    #[pin]
+    inner: Fu,
+}
+
+#[cfg(feature = "std")]
+impl<Fu> InheritScopes<Fu> {
+    pub(crate) fn new(inner: Fu) -> Self {
+        InheritScopes {
+            scopes: WRITE_SCOPES.with(|scopes| scopes.borrow().clone()),
+            inner,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Fu: Future> Future for InheritScopes<Fu> {
+    type Output = Fu::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _exit = ExitScope::enter(this.scopes);
+        this.inner.poll(cx)
+    }
+}
+
+/// The state machine behind [`AsyncFused`](crate::async_fused::AsyncFused): a lock that
+/// starts unlocked, may be write-locked by one task at a time, and is eventually either
+/// fused into a permanent read state or poisoned.
+///
+/// # Safety
+/// Implementations must grant the write lock to at most one caller at a time, and must
+/// only report [`RawOnceState::Occupied`] after [`unlock_fuse`](Self::unlock_fuse) has
+/// been called, with the appropriate memory ordering for the protected data.
 pub unsafe trait AsyncRawFused: 'static {
     type GuardMarker;
     const UNLOCKED: Self;
     const READ: Self;
     const POISON: Self;
-    fn try_write_checked(&self) -> Result<Option<RawOnceState>, PoisonError<()>>;
-    fn try_read_checked(&self) -> Result<RawOnceState, PoisonError<()>>;
+    /// Acquires the write lock without waiting, returning [`Error::WouldBlock`] if another
+    /// caller holds it.
+    fn try_write_checked(&self) -> Result<RawOnceState, Error>;
+    /// Reports the current state without waiting. A held write lock reads as vacant.
+    fn try_read_checked(&self) -> Result<RawOnceState, Error>;
+    /// Releases the write lock, leaving the state vacant.
+    ///
+    /// # Safety
+    /// The caller must hold the write lock.
     unsafe fn unlock(&self);
+    /// Releases the write lock, poisoning the state.
+    ///
+    /// # Safety
+    /// The caller must hold the write lock.
     unsafe fn unlock_poison(&self);
+    /// Releases the write lock, fusing the state into read mode.
+    ///
+    /// # Safety
+    /// The caller must hold the write lock.
     unsafe fn unlock_fuse(&self);
+    /// Moves a poisoned state back to unlocked so that the next writer can retry. Returns true
+    /// if this call cleared the poison, and false if the state was not poisoned.
+    fn clear_poison(&self) -> bool;
+    /// The number of callers currently waiting in [`write_checked`](Self::write_checked) for
+    /// the write lock to be released. Callers waiting in [`read_checked`](Self::read_checked)
+    /// are not counted.
+    fn waiters(&self) -> usize;
 
-    type WriteChecked<'a>: 'a + Future<Output = Result<RawOnceState, TryLockError<()>>>
+    /// Acquires the write lock. Implementations should return [`Error::Cycle`] instead
+    /// of waiting when called from inside a write scope for this lock.
+    type WriteChecked<'a>: 'a + Future<Output = Result<RawOnceState, Error>>
     where
         Self: 'a;
     fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a>;
 
-    // type ReadChecked<'a>: 'a + Future<Output=Result<RawOnceState, TryLockError<() > > > where Self: 'a;
-    // fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a>;
+    type ReadChecked<'a>: 'a + Future<Output = Result<RawOnceState, Error>>
+    where
+        Self: 'a;
+    /// Waits until no writer holds the lock, without acquiring it.
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a>;
+
+    /// Like [`write_checked`](Self::write_checked), but blocks the current thread. By default
+    /// the future is driven with a thread-parking executor, which panics inside a runtime.
+    ///
+    /// The blocking accessors, e.g. [`AsyncOnce::get_or_init_blocking`], take the lock through
+    /// this. The default behaves like those accessors always did, so existing implementations
+    /// are unaffected. A lock that can park threads itself, like `AsyncRawFusedHybridLock`,
+    /// overrides it.
+    ///
+    /// [`AsyncOnce::get_or_init_blocking`]: crate::async_once::AsyncOnce::get_or_init_blocking
+    #[cfg(feature = "std")]
+    fn write_checked_blocking(&self) -> Result<RawOnceState, Error> {
+        crate::blocking::block_on(self.write_checked())
+    }
 }
 
-pub trait AsyncRawFusedSync = AsyncRawFused + Sync + Send
-where
-    <Self as AsyncRawFused>::GuardMarker: Send,
-    for<'a> <Self as AsyncRawFused>::WriteChecked<'a>: Send;
+/// The future returned by [`AsyncRawFused::write_checked`] and
+/// [`AsyncRawFused::read_checked`] on stable toolchains, where it cannot be left unnamed.
+#[cfg(not(feature = "nightly"))]
+pub type RawFuture<'a> = BoxFuture<'a, Result<RawOnceState, Error>>;
+
+// Trait aliases are rejected by stable parsers even when configured out, so the alias lives in
+// a module that is only loaded on nightly.
+#[cfg(feature = "nightly")]
+pub use crate::nightly::AsyncRawFusedSync;
+
+/// A raw lock that can be shared between threads, with `Send` futures.
+///
+/// Without trait aliases the `Send` bounds on the futures could not be implied, so stable
+/// toolchains require them to be [`RawFuture`]s.
+#[cfg(not(feature = "nightly"))]
+pub trait AsyncRawFusedSync:
+    Sync
+    + Send
+    + for<'a> AsyncRawFused<
+        GuardMarker: Send,
+        WriteChecked<'a> = RawFuture<'a>,
+        ReadChecked<'a> = RawFuture<'a>,
+    >
+{
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<R> AsyncRawFusedSync for R where
+    R: Sync
+        + Send
+        + for<'a> AsyncRawFused<
+            GuardMarker: Send,
+            WriteChecked<'a> = RawFuture<'a>,
+            ReadChecked<'a> = RawFuture<'a>,
+        >
+{
+}
@@ -0,0 +1,93 @@
+use crate::async_fused::{AsyncFused, AsyncFusedEntry, PoisonPolicy};
+use crate::raw::WriteScope;
+use crate::sync::AsyncRawFusedLock;
+use parking_lot::{const_mutex, Mutex};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// One value of an [`AsyncExpiringLock`] and the instant it expires.
+type Generation<T> = AsyncFused<AsyncRawFusedLock, Option<(Arc<T>, Instant)>>;
+
+/// A cached value that expires a fixed time after it was initialized.
+///
+/// Each value lives in its own generation, an [`AsyncFused`] over an [`AsyncRawFusedLock`], so
+/// a single caller runs the initializer while the others wait on the generation's write lock.
+/// Once the value has expired, the generation is swapped for a vacant one. Hits return a cheap
+/// [`Arc`] handle.
+pub struct AsyncExpiringLock<T> {
+    ttl: Duration,
+    current: Mutex<Option<Arc<Generation<T>>>>,
+}
+
+impl<T> AsyncExpiringLock<T> {
+    pub const fn new(ttl: Duration) -> Self {
+        AsyncExpiringLock {
+            ttl,
+            current: const_mutex(None),
+        }
+    }
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+    fn generation(&self) -> Arc<Generation<T>> {
+        self.current
+            .lock()
+            .get_or_insert_with(|| {
+                Arc::new(AsyncFused::new_with_policy(
+                    None,
+                    PoisonPolicy::RetryOnPanic,
+                ))
+            })
+            .clone()
+    }
+    /// Drops `generation` so that the next caller starts a new one, unless it was already
+    /// replaced.
+    fn expire(&self, generation: &Arc<Generation<T>>) {
+        let mut current = self.current.lock();
+        if current.as_ref().is_some_and(|x| Arc::ptr_eq(x, generation)) {
+            *current = None;
+        }
+    }
+    /// Returns the value if it has not expired, without waiting.
+    pub fn try_get(&self) -> Option<Arc<T>> {
+        let generation = self.current.lock().clone()?;
+        let (value, expiry) = generation.try_read()?.as_ref()?;
+        (Instant::now() < *expiry).then(|| value.clone())
+    }
+    /// Returns the cached value, or runs `init` to replace it if it is missing or expired.
+    /// If the initializer panics or is cancelled, the next waiter runs its own.
+    pub async fn get_or_init<Fu: Future<Output = T>>(&self, init: impl FnOnce() -> Fu) -> Arc<T> {
+        loop {
+            let generation = self.generation();
+            let entry = generation.write().await;
+            match entry {
+                AsyncFusedEntry::Read(Some((value, expiry))) if Instant::now() < *expiry => {
+                    return value.clone();
+                }
+                AsyncFusedEntry::Read(_) => self.expire(&generation),
+                AsyncFusedEntry::Write(mut guard) => {
+                    guard.begin_init();
+                    let value = Arc::new(WriteScope::new(guard.scope_id(), init()).await);
+                    *guard = Some((value.clone(), Instant::now() + self.ttl));
+                    guard.fuse();
+                    return value;
+                }
+            }
+        }
+    }
+    /// Expires the value immediately. Handles that were already returned stay valid.
+    pub fn invalidate(&self) {
+        self.current.lock().take();
+    }
+}
+
+impl<T> Debug for AsyncExpiringLock<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncExpiringLock")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
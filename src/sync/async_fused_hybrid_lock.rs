@@ -0,0 +1,133 @@
+use crate::blocking::assert_not_in_runtime;
+use crate::error::Error;
+use crate::raw::{in_write_scope, AsyncRawFused, RawOnceState};
+use crate::word::AsyncRawFusedWordLock;
+use parking_lot_core::{
+    park, unpark_all, unpark_filter, FilterOp, DEFAULT_PARK_TOKEN, DEFAULT_UNPARK_TOKEN,
+};
+
+/// An [`AsyncRawFused`] whose waiters may be async tasks or blocked threads.
+///
+/// Tasks wait on the intrusive stack of an [`AsyncRawFusedWordLock`]. Threads calling
+/// [`write_checked_blocking`](AsyncRawFused::write_checked_blocking) park in `parking_lot`'s
+/// global table, keyed by the address of the lock, so neither needs an executor and the lock
+/// still fits in a single word. Releasing the write lock wakes both.
+///
+/// [`waiters`](AsyncRawFused::waiters) counts parked threads as well as tasks, so an
+/// initializer is not cancelled while a thread still waits for it.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct AsyncRawFusedHybridLock {
+    // Cycle detection identifies a lock by its address, which the wrapper shares with `inner`.
+    inner: AsyncRawFusedWordLock,
+}
+
+impl AsyncRawFusedHybridLock {
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+    /// Parks the current thread until the write lock is released, or returns immediately if it
+    /// is not held.
+    fn park(&self) {
+        assert_not_in_runtime();
+        unsafe {
+            // `validate` runs under the queue lock of the key, which `unpark_all` also takes, so
+            // a release cannot slip in between the check and parking.
+            park(
+                self.key(),
+                || self.inner.is_write_locked(),
+                || {},
+                |_, _| {},
+                DEFAULT_PARK_TOKEN,
+                None,
+            );
+        }
+    }
+    /// Counts the threads parked on this lock, skipping rather than waking them.
+    fn parked(&self) -> usize {
+        let mut count = 0;
+        unsafe {
+            unpark_filter(
+                self.key(),
+                |_| {
+                    count += 1;
+                    FilterOp::Skip
+                },
+                |_| DEFAULT_UNPARK_TOKEN,
+            );
+        }
+        count
+    }
+    fn unpark(&self) {
+        unsafe {
+            unpark_all(self.key(), DEFAULT_UNPARK_TOKEN);
+        }
+    }
+}
+
+unsafe impl AsyncRawFused for AsyncRawFusedHybridLock {
+    type GuardMarker = <AsyncRawFusedWordLock as AsyncRawFused>::GuardMarker;
+    const UNLOCKED: Self = AsyncRawFusedHybridLock {
+        inner: AsyncRawFusedWordLock::UNLOCKED,
+    };
+    const READ: Self = AsyncRawFusedHybridLock {
+        inner: AsyncRawFusedWordLock::READ,
+    };
+    const POISON: Self = AsyncRawFusedHybridLock {
+        inner: AsyncRawFusedWordLock::POISON,
+    };
+
+    fn try_write_checked(&self) -> Result<RawOnceState, Error> {
+        self.inner.try_write_checked()
+    }
+
+    fn try_read_checked(&self) -> Result<RawOnceState, Error> {
+        self.inner.try_read_checked()
+    }
+
+    unsafe fn unlock(&self) {
+        self.inner.unlock();
+        self.unpark();
+    }
+
+    unsafe fn unlock_poison(&self) {
+        self.inner.unlock_poison();
+        self.unpark();
+    }
+
+    unsafe fn unlock_fuse(&self) {
+        self.inner.unlock_fuse();
+        self.unpark();
+    }
+
+    fn clear_poison(&self) -> bool {
+        self.inner.clear_poison()
+    }
+
+    fn waiters(&self) -> usize {
+        self.inner.waiters() + self.parked()
+    }
+
+    type WriteChecked<'a> = <AsyncRawFusedWordLock as AsyncRawFused>::WriteChecked<'a>;
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        self.inner.write_checked()
+    }
+
+    type ReadChecked<'a> = <AsyncRawFusedWordLock as AsyncRawFused>::ReadChecked<'a>;
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        self.inner.read_checked()
+    }
+
+    fn write_checked_blocking(&self) -> Result<RawOnceState, Error> {
+        loop {
+            match self.try_write_checked() {
+                Err(Error::WouldBlock) => {}
+                result => return result,
+            }
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            self.park();
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use crate::raw::{AsyncRawFused, RawOnceState};
-use parking_lot::lock_api::GuardSend;
+use crate::error::Error;
+#[cfg(not(feature = "nightly"))]
+use crate::raw::RawFuture;
+use crate::raw::{in_write_scope, AsyncRawFused, GuardSend, RawOnceState};
 use std::future::Future;
+use std::pin::pin;
 use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::{Acquire, Release};
-use std::sync::{PoisonError, TryLockError};
-use tokio::sync::{Semaphore, TryAcquireError};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit, TryAcquireError};
 
 const STATE_UNINIT: usize = 0;
 const STATE_INIT: usize = 1;
@@ -13,118 +15,188 @@ const STATE_POISON: usize = 2;
 pub struct AsyncRawFusedLock {
     state: AtomicUsize,
     semaphore: Semaphore,
+    /// Notified whenever the write lock is released, for `read_checked`.
+    released: Notify,
+    waiters: AtomicUsize,
 }
 
-impl AsyncRawFusedLock {}
+/// Counts a caller of `write_checked` as waiting until it is dropped.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(waiters: &'a AtomicUsize) -> Self {
+        waiters.fetch_add(1, Relaxed);
+        Waiting(waiters)
+    }
+}
+
+impl<'a> Drop for Waiting<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Relaxed);
+    }
+}
+
+impl AsyncRawFusedLock {
+    async fn write_checked_impl(&self) -> Result<RawOnceState, Error> {
+        if let RawOnceState::Occupied = self.try_read_checked()? {
+            return Ok(RawOnceState::Occupied);
+        }
+        if in_write_scope(self) {
+            return Err(Error::Cycle);
+        }
+        let waiting = Waiting::new(&self.waiters);
+        let lock = self.semaphore.acquire().await;
+        drop(waiting);
+        match lock {
+            Ok(lock) => self.keep_permit(lock),
+            Err(_) => match self.try_read_checked()? {
+                RawOnceState::Occupied => Ok(RawOnceState::Occupied),
+                RawOnceState::Vacant => unreachable!(),
+            },
+        }
+    }
+
+    /// Keeps the permit as the write lock, unless the lock turns out to be poisoned. The permit
+    /// is then returned, and readers that saw it taken are woken as for any other release.
+    fn keep_permit(&self, lock: SemaphorePermit<'_>) -> Result<RawOnceState, Error> {
+        match self.try_read_checked() {
+            Ok(RawOnceState::Vacant) => {
+                lock.forget();
+                Ok(RawOnceState::Vacant)
+            }
+            Ok(RawOnceState::Occupied) => unreachable!(),
+            Err(e) => {
+                drop(lock);
+                self.released.notify_waiters();
+                Err(e)
+            }
+        }
+    }
+
+    /// Whether a writer currently holds the semaphore's only permit.
+    fn is_write_locked(&self) -> bool {
+        !self.semaphore.is_closed() && self.semaphore.available_permits() == 0
+    }
+
+    async fn read_checked_impl(&self) -> Result<RawOnceState, Error> {
+        loop {
+            if let RawOnceState::Occupied = self.try_read_checked()? {
+                return Ok(RawOnceState::Occupied);
+            }
+            // Register before checking, so that a release in between is not missed. Readers
+            // never take the permit, so they cannot make a concurrent `try_write_checked` fail.
+            let released = self.released.notified();
+            let mut released = pin!(released);
+            released.as_mut().enable();
+            if !self.is_write_locked() {
+                return self.try_read_checked();
+            }
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            released.await;
+        }
+    }
+}
 
 unsafe impl AsyncRawFused for AsyncRawFusedLock {
     type GuardMarker = GuardSend;
     const UNLOCKED: Self = AsyncRawFusedLock {
         state: AtomicUsize::new(STATE_UNINIT),
         semaphore: Semaphore::const_new(1),
+        released: Notify::const_new(),
+        waiters: AtomicUsize::new(0),
     };
     const READ: Self = AsyncRawFusedLock {
         state: AtomicUsize::new(STATE_INIT),
         semaphore: Semaphore::const_new(1),
+        released: Notify::const_new(),
+        waiters: AtomicUsize::new(0),
     };
     const POISON: Self = AsyncRawFusedLock {
         state: AtomicUsize::new(STATE_POISON),
         semaphore: Semaphore::const_new(1),
+        released: Notify::const_new(),
+        waiters: AtomicUsize::new(0),
     };
 
-    fn try_write_checked(&self) -> Result<Option<RawOnceState>, PoisonError<()>> {
-        match self.try_read_checked()? {
-            RawOnceState::Occupied => {
-                return Ok(Some(RawOnceState::Occupied));
-            }
-            _ => {}
+    fn try_write_checked(&self) -> Result<RawOnceState, Error> {
+        if let RawOnceState::Occupied = self.try_read_checked()? {
+            return Ok(RawOnceState::Occupied);
         }
         match self.semaphore.try_acquire() {
-            Ok(lock) => match self.try_read_checked()? {
-                RawOnceState::Occupied => unreachable!(),
-                RawOnceState::Vacant => {
-                    lock.forget();
-                    Ok(Some(RawOnceState::Vacant))
-                }
-            },
+            Ok(lock) => self.keep_permit(lock),
             Err(TryAcquireError::Closed) => match self.try_read_checked()? {
-                RawOnceState::Occupied => {
-                    return Ok(Some(RawOnceState::Occupied));
-                }
+                RawOnceState::Occupied => Ok(RawOnceState::Occupied),
                 RawOnceState::Vacant => unreachable!(),
             },
-            Err(TryAcquireError::NoPermits) => Ok(None),
+            Err(TryAcquireError::NoPermits) => Err(Error::WouldBlock),
         }
     }
 
-    fn try_read_checked(&self) -> Result<RawOnceState, PoisonError<()>> {
+    fn try_read_checked(&self) -> Result<RawOnceState, Error> {
         match self.state.load(Acquire) {
             STATE_UNINIT => Ok(RawOnceState::Vacant),
             STATE_INIT => Ok(RawOnceState::Occupied),
-            STATE_POISON => Err(PoisonError::new(())),
+            STATE_POISON => Err(Error::Poisoned),
             _ => unreachable!(),
         }
     }
 
     unsafe fn unlock(&self) {
         self.semaphore.add_permits(1);
+        self.released.notify_waiters();
     }
 
     unsafe fn unlock_poison(&self) {
+        // Unlike fusing, poisoning can be undone by `clear_poison`, so the semaphore stays open.
+        // Waiters still take their turn on it and observe the poison.
         self.state.store(STATE_POISON, Release);
-        self.semaphore.close();
+        self.semaphore.add_permits(1);
+        self.released.notify_waiters();
     }
 
     unsafe fn unlock_fuse(&self) {
         self.state.store(STATE_INIT, Release);
         self.semaphore.close();
+        self.released.notify_waiters();
     }
 
-    type WriteChecked<'a> =
-        impl 'a + Send + Future<Output = Result<RawOnceState, TryLockError<()>>>;
+    fn clear_poison(&self) -> bool {
+        self.state
+            .compare_exchange(STATE_POISON, STATE_UNINIT, AcqRel, Acquire)
+            .is_ok()
+    }
+
+    fn waiters(&self) -> usize {
+        self.waiters.load(Relaxed)
+    }
+
+    #[cfg(feature = "nightly")]
+    type WriteChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
     fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
-        async move {
-            match self.try_read_checked()? {
-                RawOnceState::Occupied => {
-                    return Ok(RawOnceState::Occupied);
-                }
-                _ => {}
-            }
-            match self.semaphore.acquire().await {
-                Ok(lock) => match self.try_read_checked()? {
-                    RawOnceState::Occupied => unreachable!(),
-                    RawOnceState::Vacant => {
-                        lock.forget();
-                        Ok(RawOnceState::Vacant)
-                    }
-                },
-                Err(_) => match self.try_read_checked()? {
-                    RawOnceState::Occupied => Ok(RawOnceState::Occupied),
-                    RawOnceState::Vacant => unreachable!(),
-                },
-            }
-        }
+        self.write_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type WriteChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        Box::pin(self.write_checked_impl())
     }
 
-    // type ReadChecked<'a> = impl 'a + Send + Future<Output=Result<RawOnceState, TryLockError<()>>>;
-    // fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
-    //     async move {
-    //         match self.try_read_checked()? {
-    //             RawOnceState::Occupied => { return Ok(RawOnceState::Occupied); }
-    //             _ => {}
-    //         }
-    //         match self.semaphore.acquire().await {
-    //             Ok(lock) => match self.try_read_checked()? {
-    //                 RawOnceState::Occupied => unreachable!(),
-    //                 RawOnceState::Vacant => Ok(RawOnceState::Vacant),
-    //             }
-    //             Err(_) => match self.try_read_checked()? {
-    //                 RawOnceState::Occupied => Ok(RawOnceState::Occupied),
-    //                 RawOnceState::Vacant => unreachable!()
-    //             }
-    //         }
-    //     }
-    // }
+    #[cfg(feature = "nightly")]
+    type ReadChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        self.read_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type ReadChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        Box::pin(self.read_checked_impl())
+    }
 }
 
 unsafe impl Send for AsyncRawFusedLock {}
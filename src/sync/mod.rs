@@ -1,14 +1,38 @@
+mod async_expiring_lock;
+mod async_fused_hybrid_lock;
 mod async_fused_lock;
 
 #[cfg(test)]
 mod test;
 
-use crate::detached::DetachedFuture;
+use crate::detached::{BoxDetached, DetachedFuture};
+pub use crate::word::AsyncRawFusedWordLock;
+pub use async_expiring_lock::AsyncExpiringLock;
+pub use async_fused_hybrid_lock::AsyncRawFusedHybridLock;
 pub use async_fused_lock::AsyncRawFusedLock;
 use std::future::Future;
 use std::pin::Pin;
 
-pub type AsyncOnceLock<F> =
-    crate::async_once::AsyncOnce<async_fused_lock::AsyncRawFusedLock, F>;
-pub type AsyncLazyLock<F> =
-    crate::async_lazy::AsyncLazy<async_fused_lock::AsyncRawFusedLock, F>;
+/// The raw lock behind the aliases below: [`AsyncRawFusedWordLock`] with the `word-lock`
+/// feature, and [`AsyncRawFusedLock`] otherwise.
+#[cfg(not(feature = "word-lock"))]
+pub type DefaultRawLock = AsyncRawFusedLock;
+#[cfg(feature = "word-lock")]
+pub type DefaultRawLock = AsyncRawFusedWordLock;
+
+pub type AsyncOnceLock<F> = crate::async_once::AsyncOnce<DefaultRawLock, F>;
+/// An [`AsyncOnceLock`] named by its output type alone, so that any future producing `T` can be
+/// passed to [`get_or_init`](crate::async_once_box::AsyncOnceBox::get_or_init).
+///
+/// This is a separate alias because the parameter of [`AsyncOnceLock`] is the initializer type:
+/// `AsyncOnceLock<JoinTransparent<usize>>` stores the spawned handle unboxed. Reading that
+/// parameter as the output instead would break those cells and box every initializer.
+pub type AsyncOnceBoxLock<T> = crate::async_once_box::AsyncOnceBox<DefaultRawLock, BoxDetached<T>>;
+pub type AsyncLazyLock<F, I = fn() -> F> = crate::async_lazy::AsyncLazy<DefaultRawLock, F, I>;
+/// An [`AsyncLazyLock`] named by its output type alone, built with
+/// [`new_boxed`](crate::async_lazy::AsyncLazy::new_boxed).
+pub type AsyncLazyBoxLock<T> = AsyncLazyLock<BoxDetached<T>>;
+pub type AsyncRefreshLock<F> = crate::async_refresh::AsyncRefresh<DefaultRawLock, F>;
+pub type AsyncTryLazyLock<F, I = fn() -> F> =
+    crate::async_try_lazy::AsyncTryLazy<DefaultRawLock, F, I>;
+pub type AsyncStaticLock<T> = crate::async_static::AsyncStatic<DefaultRawLock, T>;
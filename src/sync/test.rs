@@ -51,7 +51,7 @@
 // // fn test_recurrent() {
 // //     let once = OnceLock::<Box<isize>>::new();
 // //     once.get_or_init(|| {
-// //         assert_eq!(once.get_or_init_checked(|| unreachable!()).unwrap_err(), LockError::CycleError);
+// //         assert_eq!(once.get_or_init_checked(|| unreachable!()).unwrap_err(), Error::Cycle);
 // //         Box::new(5)
 // //     });
 // // }
@@ -144,8 +144,36 @@
 //     x.get()
 // }
 
-use crate::async_fused::{AsyncFused, AsyncFusedEntry};
+use crate::async_fused::{AsyncFused, AsyncFusedEntry, PoisonPolicy};
+use crate::async_once::{AsyncOnce, AsyncOnceEntry, DeadlineAction};
+#[cfg(feature = "tokio-rt")]
+use crate::detached::{
+    spawn_transparent, spawn_try_transparent, JoinTransparent, TokioSpawner, TryJoinTransparent,
+};
+use crate::detached::{BoxDetached, DetachedFuture, FuturesSpawner, Spawner};
+use crate::error::Error;
+use crate::raw::WriteScope;
+use crate::sync::async_fused_hybrid_lock::AsyncRawFusedHybridLock;
 use crate::sync::async_fused_lock::AsyncRawFusedLock;
+use crate::sync::{
+    AsyncExpiringLock, AsyncLazyBoxLock, AsyncLazyLock, AsyncOnceBoxLock, AsyncOnceLock,
+    AsyncStaticLock,
+};
+use crate::test_util::{check_raw_lock, check_raw_lock_threads, yield_once};
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{BoxFuture, Map};
+use futures::task::SpawnExt;
+use futures::FutureExt;
+use std::convert::identity;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_fused() {
@@ -173,3 +201,767 @@ async fn test_fused() {
     }
     println!("a");
 }
+
+#[tokio::test]
+async fn test_read() {
+    let fused = Arc::new(AsyncFused::<AsyncRawFusedLock, _>::new(1usize));
+    let read = |fused: &Arc<AsyncFused<AsyncRawFusedLock, usize>>| {
+        let fused = fused.clone();
+        tokio::spawn(async move { fused.read().await.copied() })
+    };
+    assert_eq!(fused.read().await, None);
+    let AsyncFusedEntry::Write(guard) = fused.write().await else {
+        unreachable!()
+    };
+    let reader = read(&fused);
+    tokio::task::yield_now().await;
+    drop(guard);
+    assert_eq!(reader.await.unwrap(), None);
+    let AsyncFusedEntry::Write(mut guard) = fused.write().await else {
+        unreachable!()
+    };
+    let reader = read(&fused);
+    tokio::task::yield_now().await;
+    *guard = 2;
+    guard.fuse();
+    assert_eq!(reader.await.unwrap(), Some(2));
+    assert_eq!(fused.read().await, Some(&2));
+}
+
+#[tokio::test]
+async fn test_read_does_not_block_writers() {
+    let fused = AsyncFused::<AsyncRawFusedLock, _>::new(1usize);
+    let AsyncFusedEntry::Write(guard) = fused.write().await else {
+        unreachable!()
+    };
+    let mut read = Box::pin(fused.read());
+    assert!(read.as_mut().now_or_never().is_none());
+    drop(guard);
+    let Some(AsyncFusedEntry::Write(guard)) = fused.try_write() else {
+        panic!("a waiting reader took the write lock")
+    };
+    assert!(read.as_mut().now_or_never().is_none());
+    guard.fuse();
+    assert_eq!(read.await, Some(&1));
+}
+
+#[test]
+fn test_poison_wakes_readers() {
+    // A writer that takes the permit after the poisoning briefly looks like a write lock to
+    // readers, so returning it must wake them again.
+    let pool = ThreadPool::new().unwrap();
+    for _ in 0..1000 {
+        let fused = Arc::new(AsyncFused::<AsyncRawFusedLock, _>::new(0usize));
+        let AsyncFusedEntry::Write(guard) = fused.try_write().unwrap() else {
+            unreachable!()
+        };
+        let writer = pool
+            .spawn_with_handle({
+                let fused = fused.clone();
+                async move { fused.write_checked().await.err() }
+            })
+            .unwrap();
+        let reader = pool
+            .spawn_with_handle({
+                let fused = fused.clone();
+                async move { fused.read_checked().await.err() }
+            })
+            .unwrap();
+        guard.poison();
+        assert_eq!(block_on(writer), Some(Error::Poisoned));
+        assert_eq!(block_on(reader), Some(Error::Poisoned));
+    }
+}
+
+#[tokio::test]
+async fn test_recurrent() {
+    let fused = Arc::new(AsyncFused::<AsyncRawFusedLock, _>::new(1usize));
+    let AsyncFusedEntry::Write(guard) = fused.write().await else {
+        unreachable!()
+    };
+    WriteScope::new(guard.scope_id(), async {
+        assert!(matches!(fused.write_checked().await, Err(Error::Cycle)));
+        assert_eq!(fused.read_checked().await, Err(Error::Cycle));
+    })
+    .await;
+    let reader = tokio::spawn({
+        let fused = fused.clone();
+        async move { fused.read_checked().await.map(|x| x.copied()) }
+    });
+    tokio::task::yield_now().await;
+    guard.fuse();
+    assert_eq!(reader.await.unwrap(), Ok(Some(1)));
+}
+
+#[tokio::test]
+async fn test_join_holds_guard() {
+    // Branches of one `join!` share a waker, so they must not be mistaken for a cycle.
+    let fused = AsyncFused::<AsyncRawFusedLock, _>::new(0usize);
+    let (a, b) = tokio::join!(
+        async {
+            let AsyncFusedEntry::Write(mut guard) = fused.write().await else {
+                unreachable!()
+            };
+            tokio::task::yield_now().await;
+            *guard = 1;
+            *guard.fuse()
+        },
+        async {
+            tokio::task::yield_now().await;
+            match fused.write().await {
+                AsyncFusedEntry::Read(x) => *x,
+                AsyncFusedEntry::Write(_) => unreachable!(),
+            }
+        },
+    );
+    assert_eq!((a, b), (1, 1));
+}
+
+#[tokio::test]
+async fn test_guard_handed_to_task() {
+    static FUSED: AsyncFused<AsyncRawFusedLock, usize> = AsyncFused::new(0);
+    let AsyncFusedEntry::Write(mut guard) = FUSED.write().await else {
+        unreachable!()
+    };
+    *guard = 1;
+    let writer = tokio::spawn(async move {
+        tokio::task::yield_now().await;
+        guard.fuse();
+    });
+    assert_eq!(FUSED.read_checked().await, Ok(Some(&1)));
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recurrent_lazy() {
+    static LAZY: AsyncStaticLock<Option<Error>> =
+        AsyncStaticLock::new(|| async { LAZY.get_checked().await.err() });
+    assert_eq!(LAZY.get().await, &Some(Error::Cycle));
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_recurrent_spawned() {
+    static ONCE: AsyncOnceLock<JoinTransparent<Option<Error>>> = AsyncOnceLock::new();
+    let cycle = ONCE
+        .get_or_init_fn(|| spawn_transparent(async { ONCE.lock_checked().await.err() }))
+        .await;
+    assert_eq!(cycle, &Some(Error::Cycle));
+
+    static SPAWNED: AsyncOnceLock<JoinTransparent<Option<Error>>> = AsyncOnceLock::new();
+    let cycle = SPAWNED
+        .get_or_spawn(TokioSpawner, || async {
+            SPAWNED.lock_checked().await.err()
+        })
+        .await;
+    assert_eq!(cycle, &Some(Error::Cycle));
+}
+
+#[tokio::test]
+async fn test_join_same_cell() {
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    let (a, b) = tokio::join!(
+        once.get_or_init_fn(|| BoxDetached::new(async {
+            tokio::task::yield_now().await;
+            1
+        })),
+        once.get_or_init_fn(|| unreachable!()),
+    );
+    assert_eq!((a, b), (&1, &1));
+}
+
+#[tokio::test]
+async fn test_clear_poison() {
+    fn init(f: BoxFuture<'static, usize>) -> Map<BoxFuture<'static, usize>, fn(usize) -> usize> {
+        f.map(identity)
+    }
+    let once = AsyncOnceLock::new();
+    let first = AssertUnwindSafe(once.get_or_init(init(async { panic!("init") }.boxed())));
+    assert!(first.catch_unwind().await.is_err());
+    assert!(once.is_poisoned());
+    assert!(matches!(once.lock_checked().await, Err(Error::Poisoned)));
+    once.clear_poison().await;
+    assert!(!once.is_poisoned());
+    assert_eq!(*once.get_or_init(init(async { 2 }.boxed())).await, 2);
+}
+
+#[tokio::test]
+#[should_panic(expected = "Poisoned")]
+async fn test_set_poisoned() {
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    let first =
+        AssertUnwindSafe(once.get_or_init_fn(|| BoxDetached::new(async { panic!("init") })));
+    assert!(first.catch_unwind().await.is_err());
+    let _ = once.set(1).await;
+}
+
+#[tokio::test]
+async fn test_write_recover() {
+    let fused = AsyncFused::<AsyncRawFusedLock, _>::poisoned(1usize);
+    let Err(poisoned) = fused.write_recover().await else {
+        unreachable!()
+    };
+    let mut guard = poisoned.into_inner();
+    *guard = 2;
+    guard.fuse();
+    assert!(matches!(
+        fused.write_recover().await,
+        Ok(AsyncFusedEntry::Read(&2))
+    ));
+}
+
+#[tokio::test]
+async fn test_retry_on_panic() {
+    let once = AsyncOnce::<AsyncRawFusedLock, BoxDetached<usize>>::new_with_policy(
+        PoisonPolicy::RetryOnPanic,
+    );
+    let first = once.get_or_init(BoxDetached::new(async { panic!("init") }));
+    assert!(AssertUnwindSafe(first).catch_unwind().await.is_err());
+    assert!(!once.is_poisoned());
+    assert_eq!(*once.get_or_init(BoxDetached::new(async { 2 })).await, 2);
+}
+
+#[tokio::test]
+async fn test_resume_on_cancel() {
+    let once = AsyncOnce::<AsyncRawFusedLock, BoxDetached<usize>>::new();
+    let slow = BoxDetached::new(async {
+        tokio::task::yield_now().await;
+        1
+    });
+    assert_eq!(once.get_or_init(slow).now_or_never(), None);
+    assert!(!once.is_poisoned());
+    assert_eq!(*once.get_or_init(BoxDetached::new(async { 2 })).await, 1);
+}
+
+#[tokio::test]
+async fn test_poison_on_cancel() {
+    let once = AsyncOnce::<AsyncRawFusedLock, BoxDetached<usize>>::new_with_policy(
+        PoisonPolicy::PoisonOnCancel,
+    );
+    let slow = BoxDetached::new(async {
+        tokio::task::yield_now().await;
+        1
+    });
+    assert_eq!(once.get_or_init(slow).now_or_never(), None);
+    assert!(once.is_poisoned());
+    once.clear_poison().await;
+    assert_eq!(*once.get_or_init(BoxDetached::new(async { 2 })).await, 2);
+}
+
+#[tokio::test]
+async fn test_poison_on_cancel_unlocks_idle_guards() {
+    let fused =
+        AsyncFused::<AsyncRawFusedLock, _>::new_with_policy(0usize, PoisonPolicy::PoisonOnCancel);
+    drop(fused.write().await);
+    assert!(!fused.is_poisoned());
+
+    let once = AsyncOnce::<AsyncRawFusedLock, BoxDetached<usize>>::new_with_policy(
+        PoisonPolicy::PoisonOnCancel,
+    );
+    drop(once.lock().await);
+    assert!(!once.is_poisoned());
+    assert_eq!(once.set(1).await, Ok(()));
+    assert_eq!(once.try_get(), Some(&1));
+
+    let once = AsyncOnce::<AsyncRawFusedLock, BoxDetached<Result<usize, &str>>>::new_with_policy(
+        PoisonPolicy::PoisonOnCancel,
+    );
+    let failed = once.get_or_try_init(BoxDetached::new(async { Err("retry") }));
+    assert_eq!(failed.await, Err("retry"));
+    assert!(!once.is_poisoned());
+    assert_eq!(
+        once.get_or_try_init(BoxDetached::new(async { Ok(2) }))
+            .await,
+        Ok(&2)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_expiring() {
+    let calls = AtomicUsize::new(0);
+    let fetch = || async {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        calls.fetch_add(1, Relaxed)
+    };
+    let lock = AsyncExpiringLock::new(Duration::from_secs(60));
+    assert_eq!(lock.try_get(), None);
+    let (a, b) = tokio::join!(lock.get_or_init(fetch), lock.get_or_init(fetch));
+    assert_eq!((*a, *b), (0, 0));
+    tokio::time::advance(Duration::from_secs(59)).await;
+    assert_eq!(*lock.get_or_init(fetch).await, 0);
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert_eq!(lock.try_get(), None);
+    let (a, b) = tokio::join!(lock.get_or_init(fetch), lock.get_or_init(fetch));
+    assert_eq!((*a, *b, *lock.try_get().unwrap()), (1, 1, 1));
+    lock.invalidate();
+    assert_eq!(*lock.get_or_init(fetch).await, 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_expiring_cancelled_init() {
+    let lock = AsyncExpiringLock::new(Duration::from_secs(60));
+    let slow = lock.get_or_init(|| async {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        1
+    });
+    assert_eq!(slow.now_or_never(), None);
+    assert_eq!(*lock.get_or_init(|| async { 2 }).await, 2);
+    assert_eq!(lock.try_get().as_deref(), Some(&2));
+}
+
+#[test]
+fn test_spawner_thread_pool() {
+    let pool = ThreadPool::new().unwrap();
+    let lazy = AsyncLazyLock::new_spawned(FuturesSpawner(pool), async { 5 });
+    assert_eq!(*block_on(lazy.get()), 5);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_spawner_handle() {
+    let once = AsyncOnceLock::new();
+    let handle = tokio::runtime::Handle::current();
+    assert_eq!(*once.get_or_spawn(&handle, || async { 6 }).await, 6);
+    assert_eq!(*once.get_or_spawn(&handle, || async { 7 }).await, 6);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_join_resumes_panic() {
+    let join = spawn_transparent(async { panic!("init") });
+    let payload = AssertUnwindSafe(join).catch_unwind().await.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"init"));
+    let join = spawn_transparent(futures::future::pending::<()>());
+    join.abort();
+    let payload = AssertUnwindSafe(join).catch_unwind().await.unwrap_err();
+    assert_eq!(payload.downcast_ref::<Error>(), Some(&Error::Cancelled));
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_try_join_retries() {
+    let once = AsyncOnceLock::<TryJoinTransparent<usize>>::new();
+    let init = spawn_try_transparent(async { panic!("init") });
+    assert_eq!(once.get_or_try_init(init).await, Err(Error::InitFailed));
+    let init = spawn_try_transparent(futures::future::pending());
+    init.abort();
+    assert_eq!(once.get_or_try_init(init).await, Err(Error::Cancelled));
+    assert!(!once.is_poisoned());
+    let init = spawn_try_transparent(async { 3 });
+    assert_eq!(once.get_or_try_init(init).await, Ok(&3));
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_take_aborts_init() {
+    let (start, started) = tokio::sync::oneshot::channel::<()>();
+    let (finish, finished) = tokio::sync::oneshot::channel();
+    let mut once = AsyncOnceLock::<JoinTransparent<()>>::new();
+    let init = || {
+        spawn_transparent(async move {
+            started.await.unwrap();
+            finish.send(()).unwrap();
+        })
+    };
+    assert!(once.get_or_init_fn(init).now_or_never().is_none());
+    once.take();
+    let _ = start.send(());
+    assert!(finished.await.is_err());
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_init_detached_survives_take() {
+    let (start, started) = tokio::sync::oneshot::channel::<()>();
+    let (finish, finished) = tokio::sync::oneshot::channel();
+    let mut once = AsyncOnceLock::<JoinTransparent<()>>::new();
+    let init = || {
+        spawn_transparent(async move {
+            started.await.unwrap();
+            finish.send(()).unwrap();
+        })
+    };
+    assert!(once.get_or_init_detached(init).now_or_never().is_none());
+    once.take();
+    start.send(()).unwrap();
+    assert!(finished.await.is_ok());
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_keep_alive() {
+    let (start, started) = tokio::sync::oneshot::channel::<()>();
+    let once = Arc::new(AsyncOnceLock::<JoinTransparent<usize>>::new());
+    let init = || {
+        spawn_transparent(async move {
+            started.await.unwrap();
+            5
+        })
+    };
+    assert!(once
+        .get_or_spawn_detached(TokioSpawner, init)
+        .now_or_never()
+        .is_none());
+    start.send(()).unwrap();
+    while once.try_get().is_none() {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(once.try_get(), Some(&5));
+    assert_eq!(
+        *once
+            .get_or_spawn_detached(TokioSpawner, || unreachable!())
+            .await,
+        5
+    );
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+#[should_panic(expected = "detached initializer failed: poisoned")]
+async fn test_keep_alive_panics() {
+    let once = Arc::new(AsyncOnceLock::<BoxDetached<usize>>::new());
+    once.get_or_spawn_detached(TokioSpawner, || BoxDetached::new(async { panic!("init") }))
+        .await;
+}
+
+#[test]
+fn test_keep_alive_inline() {
+    let spawner = FuturesSpawner(ThreadPool::new().unwrap());
+    let once = Arc::new(AsyncOnceLock::<BoxDetached<usize>>::new());
+    let (start, started) = futures::channel::oneshot::channel::<()>();
+    let init = || {
+        BoxDetached::new(async move {
+            started.await.unwrap();
+            6
+        })
+    };
+    assert!(once
+        .get_or_spawn_detached(&spawner, init)
+        .now_or_never()
+        .is_none());
+    start.send(()).unwrap();
+    let value = block_on(once.get_or_spawn_detached(&spawner, || unreachable!()));
+    assert_eq!(*value, 6);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_box_detached_keep_alive() {
+    let (finish, finished) = tokio::sync::oneshot::channel();
+    let mut handle = BoxDetached::new_detached(spawn_transparent(async move {
+        tokio::task::yield_now().await;
+        finish.send(()).unwrap();
+    }));
+    Pin::new(&mut handle).keep_alive();
+    drop(handle);
+    assert!(finished.await.is_ok());
+}
+
+fn sleep_then(secs: u64, x: usize) -> BoxDetached<usize> {
+    BoxDetached::new(async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        x
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_waiter_timeout() {
+    let once = AsyncOnceLock::new();
+    let timeout = Duration::from_secs(1);
+    let first = once
+        .get_or_init_timeout(|| sleep_then(10, 1), timeout)
+        .await;
+    assert_eq!(first, Err(Error::TimedOut));
+    // Nobody polls the inline initializer until the next caller resumes it.
+    tokio::time::advance(Duration::from_secs(20)).await;
+    assert_eq!(once.try_get(), None);
+    assert_eq!(*once.get_or_init(sleep_then(0, 2)).await, 1);
+
+    let lazy = AsyncLazyLock::new(sleep_then(10, 3));
+    assert_eq!(lazy.get_timeout(timeout).await, Err(Error::TimedOut));
+    assert_eq!(*lazy.get().await, 3);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test(start_paused = true)]
+async fn test_waiter_timeout_spawned() {
+    let done = Arc::new(AtomicUsize::new(0));
+    let once = AsyncOnceLock::<JoinTransparent<usize>>::new();
+    let init = || {
+        let done = done.clone();
+        spawn_transparent(async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            done.fetch_add(1, Relaxed)
+        })
+    };
+    let first = once.get_or_init_timeout(init, Duration::from_secs(1)).await;
+    assert_eq!(first, Err(Error::TimedOut));
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    assert_eq!(done.load(Relaxed), 1);
+    assert_eq!(*once.get_or_init_fn(|| unreachable!()).await, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_waiter_timeout_poisoned() {
+    let once = AsyncOnceLock::<BoxDetached<usize>>::poisoned();
+    let result = once
+        .get_or_init_timeout(|| unreachable!(), Duration::from_secs(1))
+        .await;
+    assert_eq!(result, Err(Error::Poisoned));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_init_deadline() {
+    for expired in [DeadlineAction::Unlock, DeadlineAction::Poison] {
+        let once = AsyncOnceLock::new();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+        let first = once
+            .get_or_init_deadline(|| sleep_then(10, 1), deadline, expired)
+            .await;
+        assert_eq!(first, Err(Error::TimedOut));
+        assert_eq!(once.is_poisoned(), expired == DeadlineAction::Poison);
+        once.clear_poison().await;
+        assert_eq!(*once.get_or_init(sleep_then(0, 2)).await, 2);
+    }
+}
+
+#[tokio::test]
+async fn test_try_get() {
+    let once = AsyncOnceLock::new();
+    assert_eq!(once.try_get(), None);
+    let AsyncOnceEntry::Vacant(vacant) = once.lock().await else {
+        unreachable!()
+    };
+    assert_eq!(once.try_get(), None);
+    assert_eq!(format!("{:?}", once), "AsyncOnce(<uninit>)");
+    assert_eq!(*vacant.start(BoxDetached::new(async { 1 })).await, 1);
+    assert_eq!(once.try_get(), Some(&1));
+    assert_eq!(format!("{:?}", once), "AsyncOnce(1)");
+
+    let lazy = AsyncLazyLock::new(BoxDetached::new(async { 2 }));
+    assert_eq!(lazy.try_get(), None);
+    lazy.get().await;
+    assert_eq!(lazy.try_get_checked(), Ok(Some(&2)));
+    assert_eq!(format!("{:?}", lazy), "AsyncLazy(2)");
+}
+
+#[test]
+fn test_blocking_inline() {
+    let lazy = AsyncLazyLock::new(BoxDetached::new(async { 5 }));
+    assert_eq!(*lazy.get_blocking(), 5);
+    let once = AsyncOnceLock::new();
+    assert_eq!(
+        *once.get_or_init_blocking(|| BoxDetached::new(async { 6 })),
+        6
+    );
+    assert_eq!(*once.get_or_init_blocking(|| unreachable!()), 6);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[test]
+fn test_blocking_other_runtime() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let handle = runtime.handle().clone();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let thread = std::thread::spawn(move || runtime.block_on(stopped));
+    let once = AsyncOnceLock::new();
+    let value = once.get_or_init_blocking(|| Spawner::spawn(&handle, async { 7 }));
+    assert_eq!(*value, 7);
+    stop.send(()).unwrap();
+    thread.join().unwrap().unwrap();
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+#[should_panic(expected = "from inside a tokio runtime")]
+async fn test_blocking_in_runtime() {
+    let lazy = AsyncLazyLock::new(BoxDetached::new(async { 5 }));
+    lazy.get_blocking();
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+#[should_panic(expected = "from inside a tokio runtime")]
+async fn test_blocking_init_in_runtime() {
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    once.get_or_init_blocking(|| BoxDetached::new(async { 6 }));
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+#[should_panic(expected = "from inside a tokio runtime")]
+async fn test_blocking_park_in_runtime() {
+    let once = AsyncOnce::<AsyncRawFusedHybridLock, BoxDetached<usize>>::new();
+    let _guard = once.lock().await;
+    once.get_or_init_blocking(|| unreachable!());
+}
+
+#[tokio::test]
+async fn test_set() {
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    assert_eq!(once.set(1).await, Ok(()));
+    assert_eq!(once.set(2).await, Err(2));
+    assert_eq!(*once.get_or_init_with(|| unreachable!()).await, 1);
+
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    assert_eq!(*once.get_or_init_with(|| 3).await, 3);
+    let AsyncOnceEntry::Occupied(x) = once.lock().await else {
+        unreachable!()
+    };
+    assert_eq!(*x.await, 3);
+
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new();
+    let AsyncOnceEntry::Vacant(vacant) = once.lock().await else {
+        unreachable!()
+    };
+    assert_eq!(*vacant.insert(4), 4);
+    assert_eq!(once.try_get(), Some(&4));
+
+    let once = AsyncOnceLock::<BoxDetached<usize>>::new_with_value(5);
+    assert_eq!(once.try_get(), Some(&5));
+}
+
+#[tokio::test]
+async fn test_boxed() {
+    let x = AsyncOnceBoxLock::<usize>::new();
+    assert_eq!(8, *x.get_or_init(async { 8 }).await);
+    assert_eq!(8, *x.get_or_init(async { 15 }).await);
+    assert_eq!(x.try_get(), Some(&8));
+
+    static X: AsyncOnceBoxLock<usize> = AsyncOnceBoxLock::new();
+    assert_eq!(4, *X.get_or_init_fn(|| async { 4 }).await);
+
+    let y = AsyncLazyBoxLock::<usize>::new_boxed(async { 16 });
+    assert_eq!(16, *y.get().await);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test]
+async fn test_lazy_new_with() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    static LAZY: AsyncLazyLock<JoinTransparent<usize>> = AsyncLazyLock::new_with(|| {
+        CALLS.fetch_add(1, Relaxed);
+        spawn_transparent(async { 12 })
+    });
+    assert_eq!(LAZY.try_get(), None);
+    assert_eq!(CALLS.load(Relaxed), 0);
+    assert_eq!(*LAZY.get().await, 12);
+    assert_eq!(*LAZY.get().await, 12);
+    assert_eq!(CALLS.load(Relaxed), 1);
+}
+
+#[test]
+fn test_raw_lock() {
+    check_raw_lock::<AsyncRawFusedLock>();
+}
+
+#[test]
+fn test_hybrid_lock() {
+    assert_eq!(size_of::<AsyncRawFusedHybridLock>(), size_of::<usize>());
+    check_raw_lock::<AsyncRawFusedHybridLock>();
+
+    // A thread waits for the write lock to be released. Parked threads count as waiters, so that
+    // `new_cancel_when_unused` does not cancel an initializer they are waiting for.
+    let fused = Arc::new(AsyncFused::<AsyncRawFusedHybridLock, _>::new(0usize));
+    let AsyncFusedEntry::Write(mut guard) = fused.try_write().unwrap() else {
+        unreachable!()
+    };
+    let waiter = thread::spawn({
+        let fused = fused.clone();
+        move || match fused.write_checked_blocking().unwrap() {
+            AsyncFusedEntry::Read(x) => *x,
+            AsyncFusedEntry::Write(_) => unreachable!(),
+        }
+    });
+    while guard.waiters() == 0 {
+        thread::yield_now();
+    }
+    assert_eq!(guard.waiters(), 1);
+    *guard = 1;
+    guard.fuse();
+    assert_eq!(waiter.join().unwrap(), 1);
+
+    // A thread holds the write lock while a task waits for it.
+    let pool = ThreadPool::new().unwrap();
+    let fused = Arc::new(AsyncFused::<AsyncRawFusedHybridLock, _>::new(0usize));
+    let AsyncFusedEntry::Write(guard) = fused.write_checked_blocking().unwrap() else {
+        unreachable!()
+    };
+    let task = pool
+        .spawn_with_handle({
+            let fused = fused.clone();
+            async move {
+                match fused.write().await {
+                    AsyncFusedEntry::Write(mut guard) => {
+                        *guard = 2;
+                        *guard.fuse()
+                    }
+                    AsyncFusedEntry::Read(_) => unreachable!(),
+                }
+            }
+        })
+        .unwrap();
+    while guard.waiters() == 0 {
+        thread::yield_now();
+    }
+    drop(guard);
+    assert_eq!(block_on(task), 2);
+    assert_eq!(fused.try_read(), Some(&2));
+
+    let fused = AsyncFused::<AsyncRawFusedHybridLock, _>::new(0usize);
+    let AsyncFusedEntry::Write(guard) = fused.try_write().unwrap() else {
+        unreachable!()
+    };
+    let scope = WriteScope::new(guard.scope_id(), async {
+        assert!(matches!(fused.write_checked_blocking(), Err(Error::Cycle)));
+    });
+    block_on(scope);
+    guard.poison();
+    assert!(matches!(
+        fused.write_checked_blocking(),
+        Err(Error::Poisoned)
+    ));
+}
+
+#[test]
+fn test_hybrid_lock_threads() {
+    check_raw_lock_threads::<AsyncRawFusedHybridLock>();
+    // Threads and tasks race for the same cell.
+    let pool = ThreadPool::new().unwrap();
+    for _ in 0..100 {
+        let once = Arc::new(AsyncOnce::<AsyncRawFusedHybridLock, BoxDetached<usize>>::new());
+        let inits = Arc::new(AtomicUsize::new(0));
+        let init = |inits: Arc<AtomicUsize>| {
+            move || {
+                BoxDetached::new(async move {
+                    yield_once().await;
+                    inits.fetch_add(1, Relaxed) + 10
+                })
+            }
+        };
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let once = once.clone();
+                let init = init(inits.clone());
+                pool.spawn_with_handle(async move { *once.get_or_init_fn(init).await })
+                    .unwrap()
+            })
+            .collect();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let once = once.clone();
+                let init = init(inits.clone());
+                thread::spawn(move || *once.get_or_init_blocking(init))
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(block_on(task), 10);
+        }
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 10);
+        }
+        assert_eq!(inits.load(Relaxed), 1);
+    }
+}
@@ -0,0 +1,100 @@
+//! Checks shared by the tests of the raw lock implementations.
+
+use crate::async_fused::{AsyncFused, AsyncFusedEntry};
+use crate::async_once::AsyncOnce;
+use crate::detached::BoxDetached;
+use crate::error::Error;
+use crate::raw::{AsyncRawFused, AsyncRawFusedSync, WriteScope};
+use futures::executor::{block_on, ThreadPool};
+use futures::task::SpawnExt;
+use futures::FutureExt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::task::Poll;
+
+/// Returns pending once, asking to be polled again right away.
+pub async fn yield_once() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// Exercises waiting, waiter counting, cycle detection, poisoning and fusing on a single task.
+pub fn check_raw_lock<R: AsyncRawFused>() {
+    block_on(async {
+        let fused = AsyncFused::<R, _>::new(1usize);
+        let AsyncFusedEntry::Write(mut guard) = fused.write().await else {
+            unreachable!()
+        };
+        let mut first = Box::pin(fused.write());
+        let mut second = Box::pin(fused.read());
+        let mut third = Box::pin(fused.write_checked());
+        assert!(first.as_mut().now_or_never().is_none());
+        assert!(second.as_mut().now_or_never().is_none());
+        assert!(third.as_mut().now_or_never().is_none());
+        // Only callers of `write_checked` are counted.
+        assert_eq!(guard.waiters(), 2);
+        drop(second);
+        assert_eq!(guard.waiters(), 2);
+        // Write scopes are only tracked with `std`; otherwise this would wait for itself.
+        #[cfg(feature = "std")]
+        WriteScope::new(guard.scope_id(), async {
+            assert!(matches!(fused.write_checked().await, Err(Error::Cycle)));
+        })
+        .await;
+        *guard = 2;
+        drop(guard);
+        let AsyncFusedEntry::Write(guard) = first.await else {
+            unreachable!()
+        };
+        assert_eq!(*guard, 2);
+        guard.poison();
+        assert!(matches!(third.await, Err(Error::Poisoned)));
+        assert!(fused.is_poisoned());
+        fused.clear_poison();
+        let AsyncFusedEntry::Write(guard) = fused.write().await else {
+            unreachable!()
+        };
+        guard.fuse();
+        assert_eq!(fused.read().await, Some(&2));
+    });
+}
+
+/// Races tasks on a thread pool to initialize one cell, which must run a single initializer.
+pub fn check_raw_lock_threads<R: AsyncRawFusedSync>() {
+    let pool = ThreadPool::new().unwrap();
+    for _ in 0..100 {
+        let once = Arc::new(AsyncOnce::<R, BoxDetached<usize>>::new());
+        let inits = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let once = once.clone();
+                let inits = inits.clone();
+                pool.spawn_with_handle(async move {
+                    let value = once
+                        .get_or_init_fn(|| {
+                            BoxDetached::new(async move {
+                                yield_once().await;
+                                inits.fetch_add(1, Relaxed) + 10
+                            })
+                        })
+                        .await;
+                    *value
+                })
+                .unwrap()
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(block_on(task), 10);
+        }
+        assert_eq!(inits.load(Relaxed), 1);
+    }
+}
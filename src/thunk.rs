@@ -1,9 +1,9 @@
-use std::future::Future;
-use std::mem;
-use std::pin::Pin;
-use std::sync::Exclusive;
-use std::task::{Context, Poll};
-// use crate::mut_cell::MutCell;
+use crate::mut_cell::MutCell;
+use crate::raw::panicking;
+use core::future::{poll_fn, Future};
+use core::mem;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
 
 pub enum OptionThunk<T, F> {
     Uninit,
@@ -43,21 +43,28 @@ impl<F: Future + Unpin> OptionThunk<F::Output, F> {
     //     }
     //     self.force().await
     // }
-    pub async fn force(&mut self) -> &mut F::Output {
+    /// Polls the future, storing its output once it completes. If it panics, the thunk is reset
+    /// to `Uninit` so that a retry starts a new future rather than polling the panicked one.
+    pub fn poll_force(&mut self, cx: &mut Context<'_>) -> Poll<()> {
         match self {
             OptionThunk::Uninit => unreachable!(),
-            OptionThunk::Future(ref mut f) => {
-                let output = Pin::new(f).await;
-                *self = OptionThunk::Value(output);
+            OptionThunk::Future(_) => {
+                let reset = ResetOnPanic(self);
+                let OptionThunk::Future(f) = &mut *reset.0 else {
+                    unreachable!()
+                };
+                let output = ready!(Pin::new(f).poll(cx));
+                *reset.0 = OptionThunk::Value(output);
+                Poll::Ready(())
             }
-            OptionThunk::Value(x) => return x,
-        }
-        match self {
-            OptionThunk::Uninit => unreachable!(),
-            OptionThunk::Future(_) => unreachable!(),
-            OptionThunk::Value(x) => return x,
+            OptionThunk::Value(_) => Poll::Ready(()),
         }
     }
+    /// Runs the future to completion, as [`poll_force`](Self::poll_force).
+    pub async fn force(&mut self) -> &mut F::Output {
+        poll_fn(|cx| self.poll_force(cx)).await;
+        self.get_mut().unwrap()
+    }
     pub fn get(&self) -> Option<&F::Output> {
         match self {
             OptionThunk::Future(_) => None,
@@ -65,22 +72,62 @@ impl<F: Future + Unpin> OptionThunk<F::Output, F> {
             OptionThunk::Uninit => None,
         }
     }
+    pub fn get_mut(&mut self) -> Option<&mut F::Output> {
+        match self {
+            OptionThunk::Value(x) => Some(x),
+            _ => None,
+        }
+    }
+    pub fn into_inner(self) -> Option<F::Output> {
+        match self {
+            OptionThunk::Value(x) => Some(x),
+            _ => None,
+        }
+    }
+    /// Resets to `Uninit`, returning the value if there was one.
+    pub fn take(&mut self) -> Option<F::Output> {
+        match mem::replace(self, OptionThunk::Uninit) {
+            OptionThunk::Value(x) => Some(x),
+            _ => None,
+        }
+    }
 }
 
-pub enum Thunk<T, F> {
-    Future(Exclusive<F>),
+struct ResetOnPanic<'a, T, F>(&'a mut OptionThunk<T, F>);
+
+impl<'a, T, F> Drop for ResetOnPanic<'a, T, F> {
+    fn drop(&mut self) {
+        if panicking() {
+            *self.0 = OptionThunk::Uninit;
+        }
+    }
+}
+
+pub enum Thunk<T, F, I = fn() -> F> {
+    /// The closure that builds the future. It is taken when called, so `None` is only seen if
+    /// it panicked.
+    Init(MutCell<Option<I>>),
+    Future(MutCell<F>),
     Value(T),
 }
 
-impl<F: Future + Unpin> Thunk<F::Output, F> {
+impl<F: Future + Unpin, I: FnOnce() -> F> Thunk<F::Output, F, I> {
     pub const fn new(x: F) -> Self {
-        Thunk::Future(Exclusive::new(x))
+        Thunk::Future(MutCell::new(x))
+    }
+    pub const fn new_with(init: I) -> Self {
+        Thunk::Init(MutCell::new(Some(init)))
     }
     pub const fn new_value(x: F::Output) -> Self {
         Thunk::Value(x)
     }
     pub async fn get_or_init(&mut self) -> &mut F::Output {
+        if let Thunk::Init(init) = self {
+            let init = init.as_mut().take().expect("initializer panicked");
+            *self = Thunk::Future(MutCell::new(init()));
+        }
         match self {
+            Thunk::Init(_) => unreachable!(),
             Thunk::Future(ref mut f) => {
                 let output = Pin::new(f).await;
                 *self = Thunk::Value(output);
@@ -88,13 +135,13 @@ impl<F: Future + Unpin> Thunk<F::Output, F> {
             Thunk::Value(x) => return x,
         }
         match self {
-            Thunk::Future(_) => unreachable!(),
-            Thunk::Value(x) => return x,
+            Thunk::Init(_) | Thunk::Future(_) => unreachable!(),
+            Thunk::Value(x) => x,
         }
     }
     pub fn get(&self) -> Option<&F::Output> {
         match self {
-            Thunk::Future(_) => None,
+            Thunk::Init(_) | Thunk::Future(_) => None,
             Thunk::Value(x) => Some(x),
         }
     }
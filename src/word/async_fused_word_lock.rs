@@ -0,0 +1,331 @@
+use crate::error::Error;
+#[cfg(not(feature = "nightly"))]
+use crate::raw::RawFuture;
+use crate::raw::{in_write_scope, AsyncRawFused, GuardSend, RawOnceState};
+#[cfg(not(feature = "nightly"))]
+use alloc::boxed::Box;
+use core::future::Future;
+use core::hint::spin_loop;
+use core::pin::Pin;
+use core::ptr::{self, null_mut};
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+use core::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize};
+use core::task::{Context, Poll};
+use futures::task::AtomicWaker;
+
+const STATE_UNLOCKED: usize = 0;
+const STATE_WRITE: usize = 1;
+const STATE_READ: usize = 2;
+const STATE_POISON: usize = 3;
+const STATE_MASK: usize = 0b011;
+/// Held while walking or unlinking from the waiter stack. Releasing the write lock waits for it,
+/// so that no waiter is detached while someone else is looking at it.
+const QUEUE_LOCKED: usize = 0b100;
+const FLAGS_MASK: usize = STATE_MASK | QUEUE_LOCKED;
+
+const WAITING: u8 = 0;
+const NOTIFYING: u8 = 1;
+const NOTIFIED: u8 = 2;
+
+/// An [`AsyncRawFused`] that fits in a single word and does not depend on any async runtime.
+///
+/// The low bits of the word hold the state, and the rest point to a stack of tasks waiting for
+/// the write lock to be released. It only needs atomics and `alloc`, so it is available without
+/// the `std` feature. The waiters live inside their futures. Releasing the lock wakes
+/// all of them, and they race for it again, so waiters are not served in any particular order.
+#[derive(Debug)]
+pub struct AsyncRawFusedWordLock {
+    state: AtomicUsize,
+}
+
+#[repr(align(8))]
+struct Waiter {
+    next: AtomicPtr<Waiter>,
+    waker: AtomicWaker,
+    state: AtomicU8,
+    /// Whether the waiter is in `write_checked`. Only those are counted by `waiters`.
+    writer: bool,
+}
+
+/// Waits to be detached from the stack and woken. If dropped while still on the stack, the
+/// waiter unlinks itself.
+struct Wait<'a, 'w> {
+    lock: &'a AsyncRawFusedWordLock,
+    waiter: &'w Waiter,
+}
+
+impl<'a, 'w> Unpin for Wait<'a, 'w> {}
+
+impl<'a, 'w> Future for Wait<'a, 'w> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.waiter.state.load(Acquire) != WAITING {
+            return Poll::Ready(());
+        }
+        self.waiter.waker.register(cx.waker());
+        if self.waiter.state.load(Acquire) != WAITING {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl<'a, 'w> Drop for Wait<'a, 'w> {
+    fn drop(&mut self) {
+        if self.waiter.state.load(Acquire) == WAITING && self.lock.remove(self.waiter) {
+            return;
+        }
+        // The waiter was detached by `release`, which is still using it.
+        while self.waiter.state.load(Acquire) != NOTIFIED {
+            spin_loop();
+        }
+    }
+}
+
+fn head(state: usize) -> *const Waiter {
+    (state & !FLAGS_MASK) as *const Waiter
+}
+
+impl AsyncRawFusedWordLock {
+    fn lock_queue(&self) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            if state & QUEUE_LOCKED != 0 {
+                spin_loop();
+                state = self.state.load(Relaxed);
+                continue;
+            }
+            match self
+                .state
+                .compare_exchange_weak(state, state | QUEUE_LOCKED, Acquire, Relaxed)
+            {
+                Ok(_) => return,
+                Err(actual) => state = actual,
+            }
+        }
+    }
+    fn unlock_queue(&self) {
+        self.state.fetch_and(!QUEUE_LOCKED, Release);
+    }
+    /// Pushes `waiter` onto the stack, unless the write lock has been released in the meantime.
+    fn push(&self, waiter: &Waiter) -> bool {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            if state & STATE_MASK != STATE_WRITE {
+                return false;
+            }
+            waiter.next.store(head(state) as *mut Waiter, Relaxed);
+            let new = waiter as *const Waiter as usize | (state & FLAGS_MASK);
+            match self
+                .state
+                .compare_exchange_weak(state, new, Release, Relaxed)
+            {
+                Ok(_) => return true,
+                Err(actual) => state = actual,
+            }
+        }
+    }
+    /// Unlinks `waiter` from the stack. Returns false if it was no longer on the stack.
+    fn remove(&self, waiter: &Waiter) -> bool {
+        let target = waiter as *const Waiter;
+        self.lock_queue();
+        // Only read under the queue lock, as unlinking our successor changes it.
+        let next = waiter.next.load(Relaxed);
+        let found = 'search: loop {
+            let state = self.state.load(Acquire);
+            if ptr::eq(head(state), target) {
+                let new = next as usize | (state & FLAGS_MASK);
+                match self.state.compare_exchange(state, new, AcqRel, Relaxed) {
+                    Ok(_) => break true,
+                    // Another waiter was pushed in front.
+                    Err(_) => continue,
+                }
+            }
+            let mut prev = head(state);
+            while !prev.is_null() {
+                let prev_ref = unsafe { &*prev };
+                let current = prev_ref.next.load(Relaxed);
+                if ptr::eq(current, target) {
+                    prev_ref.next.store(next, Relaxed);
+                    break 'search true;
+                }
+                prev = current;
+            }
+            break false;
+        };
+        self.unlock_queue();
+        found
+    }
+    /// Leaves the write lock in `new_state` and wakes every waiter.
+    fn release(&self, new_state: usize) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            if state & QUEUE_LOCKED != 0 {
+                spin_loop();
+                state = self.state.load(Relaxed);
+                continue;
+            }
+            assert_eq!(state & STATE_MASK, STATE_WRITE);
+            match self
+                .state
+                .compare_exchange_weak(state, new_state, AcqRel, Relaxed)
+            {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+        let mut waiter = head(state);
+        while !waiter.is_null() {
+            unsafe {
+                let next = (*waiter).next.load(Relaxed);
+                (*waiter).state.store(NOTIFYING, Release);
+                (*waiter).waker.wake();
+                // The waiter may be freed as soon as it observes this.
+                (*waiter).state.store(NOTIFIED, Release);
+                waiter = next;
+            }
+        }
+    }
+    /// Whether a writer currently holds the lock.
+    pub(crate) fn is_write_locked(&self) -> bool {
+        self.state.load(Acquire) & STATE_MASK == STATE_WRITE
+    }
+    /// Waits until the write lock is released, or returns immediately if it is not held.
+    async fn wait(&self, writer: bool) {
+        let waiter = Waiter {
+            next: AtomicPtr::new(null_mut()),
+            waker: AtomicWaker::new(),
+            state: AtomicU8::new(WAITING),
+            writer,
+        };
+        if !self.push(&waiter) {
+            return;
+        }
+        Wait {
+            lock: self,
+            waiter: &waiter,
+        }
+        .await
+    }
+    async fn write_checked_impl(&self) -> Result<RawOnceState, Error> {
+        loop {
+            match self.try_write_checked() {
+                Err(Error::WouldBlock) => {}
+                result => return result,
+            }
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            self.wait(true).await;
+        }
+    }
+    async fn read_checked_impl(&self) -> Result<RawOnceState, Error> {
+        while self.is_write_locked() {
+            if in_write_scope(self) {
+                return Err(Error::Cycle);
+            }
+            self.wait(false).await;
+        }
+        self.try_read_checked()
+    }
+}
+
+unsafe impl AsyncRawFused for AsyncRawFusedWordLock {
+    type GuardMarker = GuardSend;
+    const UNLOCKED: Self = AsyncRawFusedWordLock {
+        state: AtomicUsize::new(STATE_UNLOCKED),
+    };
+    const READ: Self = AsyncRawFusedWordLock {
+        state: AtomicUsize::new(STATE_READ),
+    };
+    const POISON: Self = AsyncRawFusedWordLock {
+        state: AtomicUsize::new(STATE_POISON),
+    };
+
+    fn try_write_checked(&self) -> Result<RawOnceState, Error> {
+        let mut state = self.state.load(Acquire);
+        loop {
+            match state & STATE_MASK {
+                STATE_UNLOCKED => {}
+                STATE_WRITE => return Err(Error::WouldBlock),
+                STATE_READ => return Ok(RawOnceState::Occupied),
+                _ => return Err(Error::Poisoned),
+            }
+            let new = (state & !STATE_MASK) | STATE_WRITE;
+            match self.state.compare_exchange(state, new, Acquire, Acquire) {
+                Ok(_) => return Ok(RawOnceState::Vacant),
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    fn try_read_checked(&self) -> Result<RawOnceState, Error> {
+        match self.state.load(Acquire) & STATE_MASK {
+            STATE_UNLOCKED | STATE_WRITE => Ok(RawOnceState::Vacant),
+            STATE_READ => Ok(RawOnceState::Occupied),
+            _ => Err(Error::Poisoned),
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.release(STATE_UNLOCKED);
+    }
+
+    unsafe fn unlock_poison(&self) {
+        self.release(STATE_POISON);
+    }
+
+    unsafe fn unlock_fuse(&self) {
+        self.release(STATE_READ);
+    }
+
+    fn clear_poison(&self) -> bool {
+        self.state
+            .fetch_update(AcqRel, Acquire, |state| {
+                (state & STATE_MASK == STATE_POISON).then_some(state & !STATE_MASK | STATE_UNLOCKED)
+            })
+            .is_ok()
+    }
+
+    fn waiters(&self) -> usize {
+        if head(self.state.load(Relaxed)).is_null() {
+            return 0;
+        }
+        self.lock_queue();
+        let mut count = 0;
+        let mut waiter = head(self.state.load(Acquire));
+        while !waiter.is_null() {
+            let waiter_ref = unsafe { &*waiter };
+            count += usize::from(waiter_ref.writer);
+            waiter = waiter_ref.next.load(Relaxed);
+        }
+        self.unlock_queue();
+        count
+    }
+
+    #[cfg(feature = "nightly")]
+    type WriteChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        self.write_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type WriteChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn write_checked<'a>(&'a self) -> Self::WriteChecked<'a> {
+        Box::pin(self.write_checked_impl())
+    }
+
+    #[cfg(feature = "nightly")]
+    type ReadChecked<'a> = impl 'a + Send + Future<Output = Result<RawOnceState, Error>>;
+    #[cfg(feature = "nightly")]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        self.read_checked_impl()
+    }
+    #[cfg(not(feature = "nightly"))]
+    type ReadChecked<'a> = RawFuture<'a>;
+    #[cfg(not(feature = "nightly"))]
+    fn read_checked<'a>(&'a self) -> Self::ReadChecked<'a> {
+        Box::pin(self.read_checked_impl())
+    }
+}
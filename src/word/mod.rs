@@ -0,0 +1,20 @@
+mod async_fused_word_lock;
+
+#[cfg(test)]
+mod test;
+
+use crate::detached::BoxDetached;
+pub use async_fused_word_lock::AsyncRawFusedWordLock;
+
+pub type AsyncOnceWord<F> = crate::async_once::AsyncOnce<AsyncRawFusedWordLock, F>;
+/// An [`AsyncOnceWord`] named by its output type alone.
+pub type AsyncOnceBoxWord<T> =
+    crate::async_once_box::AsyncOnceBox<AsyncRawFusedWordLock, BoxDetached<T>>;
+pub type AsyncLazyWord<F, I = fn() -> F> =
+    crate::async_lazy::AsyncLazy<AsyncRawFusedWordLock, F, I>;
+/// An [`AsyncLazyWord`] named by its output type alone, built with
+/// [`new_boxed`](crate::async_lazy::AsyncLazy::new_boxed).
+pub type AsyncLazyBoxWord<T> = AsyncLazyWord<BoxDetached<T>>;
+pub type AsyncTryLazyWord<F, I = fn() -> F> =
+    crate::async_try_lazy::AsyncTryLazy<AsyncRawFusedWordLock, F, I>;
+pub type AsyncStaticWord<T> = crate::async_static::AsyncStatic<AsyncRawFusedWordLock, T>;
@@ -0,0 +1,14 @@
+use crate::test_util::{check_raw_lock, check_raw_lock_threads};
+use crate::word::AsyncRawFusedWordLock;
+use std::mem::size_of;
+
+#[test]
+fn test_word_lock() {
+    assert_eq!(size_of::<AsyncRawFusedWordLock>(), size_of::<usize>());
+    check_raw_lock::<AsyncRawFusedWordLock>();
+}
+
+#[test]
+fn test_word_lock_threads() {
+    check_raw_lock_threads::<AsyncRawFusedWordLock>();
+}